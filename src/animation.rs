@@ -0,0 +1,150 @@
+use derm_rs::error::{Error, Result};
+
+use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+use image::{AnimationDecoder, DynamicImage, ImageFormat, ImageReader};
+use std::io::{Cursor, Read};
+use std::time::Duration;
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnimationMode {
+    /// Play the frames on the terminal with their delays, falls back to `sequence` when
+    /// output isn't a terminal
+    #[default]
+    Play,
+
+    /// Print every frame, separated by blank lines
+    Sequence,
+}
+
+pub struct Frame {
+    pub image: DynamicImage,
+    pub delay: Duration,
+}
+
+fn collect_frames<'a>(decoder: impl AnimationDecoder<'a>) -> Result<Vec<Frame>> {
+    decoder
+        .into_frames()
+        .map(|frame| -> Result<Frame> {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+
+            Ok(Frame {
+                delay: Duration::from_secs_f64(f64::from(numer) / f64::from(denom.max(1)) / 1000.0),
+                image: DynamicImage::ImageRgba8(frame.into_buffer()),
+            })
+        })
+        .collect()
+}
+
+/// Reads an image file, or stdin when `path` is `-`
+fn read_input(path: &str) -> Result<Vec<u8>> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+
+    std::fs::read(path).map_err(|_| Error::Open(path.to_string()))
+}
+
+/// Decodes every frame of an animated GIF, APNG or WebP, still images yield a single frame.
+/// The format is sniffed from the bytes.
+pub fn decode_frames(bytes: &[u8]) -> Result<Vec<Frame>> {
+    let open = || Cursor::new(bytes);
+
+    let format = ImageReader::new(open()).with_guessed_format()?.format();
+
+    let frames = match format {
+        Some(ImageFormat::Gif) => Some(collect_frames(GifDecoder::new(open())?)?),
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(open())?;
+            if decoder.is_apng()? {
+                Some(collect_frames(decoder.apng()?)?)
+            } else {
+                None
+            }
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(open())?;
+            if decoder.has_animation() {
+                Some(collect_frames(decoder)?)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    match frames {
+        Some(frames) if !frames.is_empty() => Ok(frames),
+        _ => Ok(vec![Frame {
+            image: image::load_from_memory(bytes)?,
+            delay: Duration::ZERO,
+        }]),
+    }
+}
+
+/// Decodes every frame of an image file, or of image bytes read from stdin when `path` is
+/// `-`
+pub fn load_frames(path: &str) -> Result<Vec<Frame>> {
+    decode_frames(&read_input(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, RgbaImage};
+
+    #[test]
+    fn still_images_decode_to_one_frame() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(5, 3))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let expected = vec![(5, 3)];
+        let result: Vec<_> = decode_frames(&png)
+            .unwrap()
+            .iter()
+            .map(|frame| (frame.image.width(), frame.image.height()))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn gifs_decode_every_frame() {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            for (shade, ms) in [(0, 50), (128, 120), (255, 200)] {
+                let pixels = RgbaImage::from_pixel(4, 2, image::Rgba([shade, shade, shade, 255]));
+                let delay = Delay::from_numer_denom_ms(ms, 1);
+                encoder
+                    .encode_frame(image::Frame::from_parts(pixels, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+
+        let expected = vec![
+            ((4, 2), Duration::from_millis(50)),
+            ((4, 2), Duration::from_millis(120)),
+            ((4, 2), Duration::from_millis(200)),
+        ];
+        let result: Vec<_> = decode_frames(&gif)
+            .unwrap()
+            .iter()
+            .map(|frame| ((frame.image.width(), frame.image.height()), frame.delay))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
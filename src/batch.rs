@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    InvalidPattern(String, String),
+    NoMatches(String),
+    UnknownPlaceholder(String),
+    DuplicateOutput(PathBuf, String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidPattern(pattern, reason) => {
+                write!(f, "Invalid pattern '{pattern}': {reason}")
+            }
+            Error::NoMatches(input) => write!(f, "No images found for '{input}'"),
+            Error::UnknownPlaceholder(name) => write!(
+                f,
+                "Unknown placeholder '{{{name}}}', expected {{stem}}, {{name}}, {{index}} or {{ext}}"
+            ),
+            Error::DuplicateOutput(path, template) => write!(
+                f,
+                "Several images would be written to {} by '{template}', add {{index}} to the name template",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn is_image(path: &Path) -> bool {
+    path.is_file() && image::ImageFormat::from_path(path).is_ok()
+}
+
+/// Images of every input, in order: a directory's images sorted by name, the files a glob
+/// pattern matches or the input itself
+pub fn expand(inputs: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut images = Vec::new();
+
+    for input in inputs {
+        let path = Path::new(input);
+        let found: Vec<PathBuf> = if path.is_dir() {
+            let mut entries: Vec<_> = std::fs::read_dir(path)
+                .map_err(|_| Error::NoMatches(input.clone()))?
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| is_image(path))
+                .collect();
+            entries.sort();
+            entries
+        } else if !path.exists() && input.contains(['*', '?', '[']) {
+            glob::glob(input)
+                .map_err(|e| Error::InvalidPattern(input.clone(), e.msg.to_string()))?
+                .filter_map(Result::ok)
+                .filter(|path| is_image(path))
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        if found.is_empty() {
+            return Err(Error::NoMatches(input.clone()));
+        }
+        images.extend(found);
+    }
+
+    Ok(images)
+}
+
+/// Fills in a file name template: `{stem}` is the input's file name without its extension,
+/// `{name}` its whole file name, `{index}` its position among the inputs from 0 and `{ext}`
+/// the output format's extension
+pub fn output_name(template: &str, input: &Path, index: usize, ext: &str) -> Result<String, Error> {
+    let file_name = |name: Option<&std::ffi::OsStr>| {
+        name.map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some((before, after)) = rest.split_once('{') {
+        let (placeholder, after) = after
+            .split_once('}')
+            .ok_or_else(|| Error::UnknownPlaceholder(after.to_string()))?;
+
+        name.push_str(before);
+        match placeholder {
+            "stem" => name.push_str(&file_name(input.file_stem())),
+            "name" => name.push_str(&file_name(input.file_name())),
+            "index" => name.push_str(&index.to_string()),
+            "ext" => name.push_str(ext),
+            _ => return Err(Error::UnknownPlaceholder(placeholder.to_string())),
+        }
+        rest = after;
+    }
+    name.push_str(rest);
+
+    Ok(name)
+}
+
+/// Files of every image in `dir`, named after `template`, failing when two images would
+/// overwrite each other
+pub fn output_paths(
+    template: &str,
+    images: &[PathBuf],
+    dir: &Path,
+    ext: &str,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut seen = std::collections::HashSet::new();
+
+    images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let path = dir.join(output_name(template, image, i, ext)?);
+            if !seen.insert(path.clone()) {
+                return Err(Error::DuplicateOutput(path, template.to_string()));
+            }
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templated_output_names() {
+        let input = Path::new("shots/cat.final.png");
+
+        let expected = vec![
+            Ok(String::from("cat.final.svg")),
+            Ok(String::from("007-cat.final.png.txt")),
+            Err(Error::UnknownPlaceholder(String::from("size"))),
+        ];
+        let result = vec![
+            output_name("{stem}.{ext}", input, 7, "svg"),
+            output_name("00{index}-{name}.{ext}", input, 7, "txt"),
+            output_name("{stem}-{size}.{ext}", input, 7, "txt"),
+        ];
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn colliding_output_names() {
+        let images = vec![PathBuf::from("d1/cat.pgm"), PathBuf::from("d2/cat.pgm")];
+        let dir = Path::new("out");
+
+        let expected = vec![
+            Err(Error::DuplicateOutput(
+                PathBuf::from("out/cat.txt"),
+                String::from("{stem}.{ext}"),
+            )),
+            Ok(vec![
+                PathBuf::from("out/0-cat.txt"),
+                PathBuf::from("out/1-cat.txt"),
+            ]),
+        ];
+        let result = vec![
+            output_paths("{stem}.{ext}", &images, dir, "txt"),
+            output_paths("{index}-{stem}.{ext}", &images, dir, "txt"),
+        ];
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
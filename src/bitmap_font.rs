@@ -0,0 +1,803 @@
+use crate::similarity::Points;
+
+use std::io::Read;
+
+type Result<T> = std::result::Result<T, Error>;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PCF_MAGIC: [u8; 4] = *b"\x01fcp";
+const BDF_MAGIC: &[u8] = b"STARTFONT";
+
+// PCF table types
+const PCF_PROPERTIES: u32 = 1 << 0;
+const PCF_ACCELERATORS: u32 = 1 << 1;
+const PCF_METRICS: u32 = 1 << 2;
+const PCF_BITMAPS: u32 = 1 << 3;
+const PCF_BDF_ENCODINGS: u32 = 1 << 5;
+const PCF_BDF_ACCELERATORS: u32 = 1 << 8;
+
+// PCF table format flags
+const PCF_BYTE_MSB_FIRST: u32 = 1 << 2;
+const PCF_BIT_MSB_FIRST: u32 = 1 << 3;
+const PCF_COMPRESSED_METRICS: u32 = 0x100;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    UnknownFormat,
+    Truncated,
+    Decompress(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownFormat => write!(f, "Not a PSF, BDF or PCF font"),
+            Error::Truncated => write!(f, "Font data ends unexpectedly"),
+            Error::Decompress(e) => write!(f, "Unable to decompress font: {e}"),
+            Error::Invalid(reason) => write!(f, "Invalid bitmap font: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A fixed size font whose glyphs are already pixels, as used by the Linux console and
+/// embedded displays
+pub struct BitmapFont {
+    name: Option<String>,
+    width: u32,
+    height: u32,
+
+    /// Ink of every glyph within the cell, sorted by char
+    glyphs: Vec<(char, Points)>,
+}
+
+impl BitmapFont {
+    /// Whether data, possibly gzipped, looks like a PSF, BDF or PCF font
+    #[must_use]
+    pub fn is_bitmap(data: &[u8]) -> bool {
+        data.starts_with(&GZIP_MAGIC)
+            || data.starts_with(&PSF1_MAGIC)
+            || data.starts_with(&PSF2_MAGIC)
+            || data.starts_with(&PCF_MAGIC)
+            || data.starts_with(BDF_MAGIC)
+    }
+
+    /// Parses a PSF (version 1 or 2), BDF or PCF font, any of which may be gzipped
+    ///
+    /// # Errors
+    ///
+    /// When the data is in none of these formats, or is cut short or inconsistent
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            flate2::read::GzDecoder::new(data)
+                .read_to_end(&mut decompressed)
+                .map_err(|e| Error::Decompress(e.to_string()))?;
+            return BitmapFont::parse(&decompressed);
+        }
+
+        let font = if data.starts_with(&PSF1_MAGIC) {
+            psf1(data)
+        } else if data.starts_with(&PSF2_MAGIC) {
+            psf2(data)
+        } else if data.starts_with(&PCF_MAGIC) {
+            pcf(data)
+        } else if data.starts_with(BDF_MAGIC) {
+            bdf(&String::from_utf8_lossy(data))
+        } else {
+            Err(Error::UnknownFormat)
+        }?;
+
+        // cells are what images get partitioned by
+        match font.cell_size() {
+            (0, _) | (_, 0) => Err(Error::Invalid(String::from("glyph cells can't be empty"))),
+            _ => Ok(font),
+        }
+    }
+
+    fn new(
+        name: Option<String>,
+        (width, height): (u32, u32),
+        mut glyphs: Vec<(char, Points)>,
+    ) -> Self {
+        // the first glyph mapped to a char wins, as it would on the console
+        glyphs.sort_by_key(|(c, _)| *c);
+        glyphs.dedup_by_key(|(c, _)| *c);
+
+        BitmapFont {
+            name,
+            width,
+            height,
+            glyphs,
+        }
+    }
+
+    /// Names fonts that don't carry their own name, like PSF fonts
+    #[must_use]
+    pub fn or_named(mut self, name: &str) -> Self {
+        self.name.get_or_insert_with(|| name.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Width and height of every glyph's cell in pixels
+    #[must_use]
+    pub fn cell_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.glyphs.iter().map(|(c, _)| *c)
+    }
+
+    /// Ink of a glyph, relative to the top left corner of its cell
+    #[must_use]
+    pub fn glyph(&self, c: char) -> Option<&Points> {
+        self.glyphs
+            .binary_search_by_key(&c, |(glyph, _)| *glyph)
+            .ok()
+            .map(|i| &self.glyphs[i].1)
+    }
+
+    #[must_use]
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.glyph(c).is_some()
+    }
+}
+
+/// Set bits of a glyph bitmap whose rows are `stride` bytes, most significant bit first.
+/// The bitmap's top left corner is placed at `origin` within the cell and anything outside
+/// the cell is dropped.
+fn bits(
+    bitmap: &[u8],
+    (width, height): (usize, usize),
+    stride: usize,
+    origin: (i64, i64),
+    cell: (u32, u32),
+) -> Points {
+    let mut points = Points::new();
+
+    for (row, bytes) in bitmap.chunks(stride.max(1)).take(height).enumerate() {
+        for column in 0..width.min(bytes.len() * 8) {
+            if bytes[column / 8] & (0x80 >> (column % 8)) == 0 {
+                continue;
+            }
+
+            let x = origin.0 + i64::try_from(column).unwrap_or(i64::MAX);
+            let y = origin.1 + i64::try_from(row).unwrap_or(i64::MAX);
+            if let (Ok(x), Ok(y)) = (u16::try_from(x), u16::try_from(y)) {
+                if u32::from(x) < cell.0 && u32::from(y) < cell.1 {
+                    points.insert((x, y));
+                }
+            }
+        }
+    }
+
+    points
+}
+
+/// Bounds checked reads of binary font data
+struct Bytes<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Bytes<'a> {
+    fn slice(&self, at: usize, len: usize) -> Result<&'a [u8]> {
+        self.data
+            .get(at..at.checked_add(len).ok_or(Error::Truncated)?)
+            .ok_or(Error::Truncated)
+    }
+
+    fn array<const N: usize>(&self, at: usize) -> Result<[u8; N]> {
+        let mut bytes: [u8; N] = self
+            .slice(at, N)?
+            .try_into()
+            .map_err(|_| Error::Truncated)?;
+        if !self.big_endian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn u8(&self, at: usize) -> Result<u8> {
+        self.data.get(at).copied().ok_or(Error::Truncated)
+    }
+
+    fn u16(&self, at: usize) -> Result<u16> {
+        self.array(at).map(u16::from_be_bytes)
+    }
+
+    fn i16(&self, at: usize) -> Result<i16> {
+        self.array(at).map(i16::from_be_bytes)
+    }
+
+    fn u32(&self, at: usize) -> Result<u32> {
+        self.array(at).map(u32::from_be_bytes)
+    }
+
+    fn i32(&self, at: usize) -> Result<i32> {
+        self.array(at).map(i32::from_be_bytes)
+    }
+
+    fn usize(&self, at: usize) -> Result<usize> {
+        usize::try_from(self.u32(at)?).map_err(|_| Error::Truncated)
+    }
+}
+
+/// Chars of each glyph of a PSF font, from its unicode table or else the glyph's position
+fn psf_glyphs(
+    name: Option<String>,
+    cell: (u32, u32),
+    bitmaps: &[u8],
+    mapping: &[(char, usize)],
+) -> BitmapFont {
+    let (width, height) = (cell.0 as usize, cell.1 as usize);
+    let stride = width.div_ceil(8);
+    let size = stride * height;
+
+    let glyphs = mapping
+        .iter()
+        .filter_map(|&(c, i)| {
+            let bitmap = bitmaps.get(i * size..(i + 1) * size)?;
+            Some((c, bits(bitmap, (width, height), stride, (0, 0), cell)))
+        })
+        .collect();
+
+    BitmapFont::new(name, cell, glyphs)
+}
+
+/// Glyph positions as chars, for PSF fonts without a unicode table
+fn positions(count: usize) -> Vec<(char, usize)> {
+    (0..count)
+        .filter_map(|i| Some((char::from_u32(u32::try_from(i).ok()?)?, i)))
+        .collect()
+}
+
+fn psf1(data: &[u8]) -> Result<BitmapFont> {
+    let bytes = Bytes {
+        data,
+        big_endian: false,
+    };
+    let mode = bytes.u8(2)?;
+    let height = bytes.u8(3)?;
+    let count = if mode & 0x01 == 0 { 256 } else { 512 };
+    let bitmaps = bytes.slice(4, count * usize::from(height))?;
+
+    let mapping = if mode & 0x06 == 0 {
+        positions(count)
+    } else {
+        // per glyph, its chars followed by sequences after 0xfffe, ending with 0xffff
+        let table = &data[4 + bitmaps.len()..];
+        let mut entries = table
+            .chunks_exact(2)
+            .map(|e| u16::from_le_bytes([e[0], e[1]]));
+        let mut mapping = Vec::new();
+
+        for i in 0..count {
+            let mut in_sequences = false;
+            for entry in entries.by_ref().take_while(|e| *e != 0xffff) {
+                in_sequences |= entry == 0xfffe;
+                if let Some(c) = char::from_u32(entry.into()).filter(|_| !in_sequences) {
+                    mapping.push((c, i));
+                }
+            }
+        }
+        mapping
+    };
+
+    Ok(psf_glyphs(None, (8, height.into()), bitmaps, &mapping))
+}
+
+fn psf2(data: &[u8]) -> Result<BitmapFont> {
+    let bytes = Bytes {
+        data,
+        big_endian: false,
+    };
+    let header_size = bytes.usize(8)?;
+    let flags = bytes.u32(12)?;
+    let count = bytes.usize(16)?;
+    let glyph_size = bytes.usize(20)?;
+    let (height, width) = (bytes.u32(24)?, bytes.u32(28)?);
+
+    if glyph_size != (width as usize).div_ceil(8) * height as usize {
+        return Err(Error::Invalid(format!(
+            "{glyph_size} byte glyphs can't be {width}x{height}"
+        )));
+    }
+    let bitmaps = bytes.slice(header_size, count * glyph_size)?;
+
+    let mapping = if flags & 0x01 == 0 {
+        positions(count)
+    } else {
+        // per glyph, UTF-8 chars followed by sequences after 0xfe, ending with 0xff
+        let table = &data[header_size + bitmaps.len()..];
+        table
+            .split(|b| *b == 0xff)
+            .take(count)
+            .enumerate()
+            .flat_map(|(i, entry)| {
+                let chars = entry.split(|b| *b == 0xfe).next().unwrap_or_default();
+                String::from_utf8_lossy(chars)
+                    .chars()
+                    .filter(|c| *c != char::REPLACEMENT_CHARACTER)
+                    .map(|c| (c, i))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+
+    Ok(psf_glyphs(None, (width, height), bitmaps, &mapping))
+}
+
+/// `x`, `y`, `width` and `height` of a BDF bounding box
+fn bdf_box(values: &[&str]) -> Option<(i64, i64, i64, i64)> {
+    let value = |i: usize| values.get(i)?.parse().ok();
+    Some((value(2)?, value(3)?, value(0)?, value(1)?))
+}
+
+fn bdf(text: &str) -> Result<BitmapFont> {
+    let invalid = |reason: &str| Error::Invalid(String::from(reason));
+
+    let mut name = None;
+    let mut bounds = None;
+    let (mut ascent, mut descent) = (None, None);
+
+    // glyphs as read, placed once the font's ascent is known
+    let mut chars = Vec::new();
+    let mut encoding = None;
+    let mut glyph_box = None;
+    let mut bitmap: Option<Vec<Vec<u8>>> = None;
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let values: Vec<&str> = words.collect();
+
+        if let Some(rows) = &mut bitmap {
+            if keyword == "ENDCHAR" {
+                if let Some(c) = encoding.take().and_then(char::from_u32) {
+                    chars.push((c, glyph_box.or(bounds), bitmap.take().unwrap_or_default()));
+                }
+                bitmap = None;
+            } else {
+                if !keyword.is_ascii() || keyword.len() % 2 != 0 {
+                    return Err(invalid("bitmap rows must be hexadecimal"));
+                }
+                let row = (0..keyword.len() / 2)
+                    .map(|i| u8::from_str_radix(&keyword[2 * i..2 * i + 2], 16))
+                    .collect::<std::result::Result<_, _>>()
+                    .map_err(|_| invalid("bitmap rows must be hexadecimal"))?;
+                rows.push(row);
+            }
+            continue;
+        }
+
+        match keyword {
+            "FAMILY_NAME" => {
+                name = Some(line[keyword.len()..].trim().trim_matches('"').to_string());
+            }
+            "FONT" if name.is_none() => name = values.first().map(ToString::to_string),
+            "FONTBOUNDINGBOX" => bounds = bdf_box(&values),
+            "FONT_ASCENT" => ascent = values.first().and_then(|v| v.parse::<i64>().ok()),
+            "FONT_DESCENT" => descent = values.first().and_then(|v| v.parse::<i64>().ok()),
+            "STARTCHAR" => (encoding, glyph_box) = (None, None),
+            "ENCODING" => {
+                encoding = values
+                    .first()
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .and_then(|v| u32::try_from(v).ok());
+            }
+            "BBX" => glyph_box = bdf_box(&values),
+            "BITMAP" => bitmap = Some(Vec::new()),
+            _ => {}
+        }
+    }
+
+    let (x, y, width, height) = bounds.ok_or_else(|| invalid("missing FONTBOUNDINGBOX"))?;
+    let ascent = ascent.unwrap_or(height + y);
+    let descent = descent.unwrap_or(-y);
+    let cell = (
+        u32::try_from(width).map_err(|_| invalid("negative width"))?,
+        u32::try_from(ascent + descent).map_err(|_| invalid("negative height"))?,
+    );
+
+    let glyphs = chars
+        .into_iter()
+        .filter_map(|(c, glyph_box, rows)| {
+            let (glyph_x, glyph_y, glyph_width, glyph_height) = glyph_box?;
+            let stride = rows.first().map_or(1, Vec::len);
+            let origin = (glyph_x - x, ascent - glyph_y - glyph_height);
+            let size = (
+                usize::try_from(glyph_width).ok()?,
+                usize::try_from(glyph_height).ok()?,
+            );
+
+            Some((c, bits(&rows.concat(), size, stride, origin, cell)))
+        })
+        .collect();
+
+    Ok(BitmapFont::new(name, cell, glyphs))
+}
+
+/// Horizontal bearings, width, ascent and descent of a PCF glyph
+struct PcfMetrics {
+    left: i64,
+    right: i64,
+    width: i64,
+    ascent: i64,
+    descent: i64,
+}
+
+/// Format and contents of a PCF table, whose format sets its byte order
+fn pcf_table(data: &[u8], kind: u32) -> Result<Option<(u32, Bytes<'_>)>> {
+    let toc = Bytes {
+        data,
+        big_endian: false,
+    };
+
+    for i in 0..toc.usize(4)? {
+        let entry = 8 + 16 * i;
+        if toc.u32(entry)? == kind {
+            let (size, offset) = (toc.usize(entry + 8)?, toc.usize(entry + 12)?);
+            let format = toc.u32(offset)?;
+            let bytes = Bytes {
+                data: toc.slice(offset, size)?,
+                big_endian: format & PCF_BYTE_MSB_FIRST != 0,
+            };
+            return Ok(Some((format, bytes)));
+        }
+    }
+
+    Ok(None)
+}
+
+fn pcf_metrics(format: u32, metrics: &Bytes) -> Result<Vec<PcfMetrics>> {
+    if format & PCF_COMPRESSED_METRICS == 0 {
+        (0..metrics.usize(4)?)
+            .map(|i| {
+                let at = 8 + 12 * i;
+                Ok(PcfMetrics {
+                    left: metrics.i16(at)?.into(),
+                    right: metrics.i16(at + 2)?.into(),
+                    width: metrics.i16(at + 4)?.into(),
+                    ascent: metrics.i16(at + 6)?.into(),
+                    descent: metrics.i16(at + 8)?.into(),
+                })
+            })
+            .collect()
+    } else {
+        let value = |at: usize| Ok(i64::from(metrics.u8(at)?) - 0x80);
+        (0..usize::from(metrics.u16(4)?))
+            .map(|i| {
+                let at = 6 + 5 * i;
+                Ok(PcfMetrics {
+                    left: value(at)?,
+                    right: value(at + 1)?,
+                    width: value(at + 2)?,
+                    ascent: value(at + 3)?,
+                    descent: value(at + 4)?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn pcf(data: &[u8]) -> Result<BitmapFont> {
+    let table = |kind: u32| pcf_table(data, kind);
+    let missing = |name: &str| Error::Invalid(format!("missing {name} table"));
+
+    let (format, metrics) = table(PCF_METRICS)?.ok_or_else(|| missing("metrics"))?;
+    let metrics = pcf_metrics(format, &metrics)?;
+
+    let (ascent, descent) = match table(PCF_BDF_ACCELERATORS)?.or(table(PCF_ACCELERATORS)?) {
+        Some((_, accelerators)) => (
+            i64::from(accelerators.i32(12)?),
+            i64::from(accelerators.i32(16)?),
+        ),
+        None => (
+            metrics.iter().map(|m| m.ascent).max().unwrap_or(0),
+            metrics.iter().map(|m| m.descent).max().unwrap_or(0),
+        ),
+    };
+    let width = metrics.iter().map(|m| m.width).max().unwrap_or(0);
+    let cell = (
+        u32::try_from(width).map_err(|_| Error::Invalid(String::from("negative width")))?,
+        u32::try_from(ascent + descent)
+            .map_err(|_| Error::Invalid(String::from("negative height")))?,
+    );
+
+    let (format, bitmaps) = table(PCF_BITMAPS)?.ok_or_else(|| missing("bitmaps"))?;
+    let count = bitmaps.usize(4)?;
+    let data_start = 8 + 4 * count + 16;
+    let pad = 1 << (format & 3);
+    let unit = 1 << ((format >> 4) & 3);
+    let glyph = |i: usize| -> Result<Points> {
+        let m = metrics.get(i).ok_or(Error::Truncated)?;
+        let size = (
+            usize::try_from(m.right - m.left).unwrap_or(0),
+            usize::try_from(m.ascent + m.descent).unwrap_or(0),
+        );
+        let stride = size.0.div_ceil(8 * pad) * pad;
+        let mut bitmap = bitmaps
+            .slice(data_start + bitmaps.usize(8 + 4 * i)?, stride * size.1)?
+            .to_vec();
+
+        if (format & PCF_BYTE_MSB_FIRST != 0) != (format & PCF_BIT_MSB_FIRST != 0) {
+            for chunk in bitmap.chunks_mut(unit) {
+                chunk.reverse();
+            }
+        }
+        if format & PCF_BIT_MSB_FIRST == 0 {
+            for byte in &mut bitmap {
+                *byte = byte.reverse_bits();
+            }
+        }
+
+        Ok(bits(
+            &bitmap,
+            size,
+            stride,
+            (m.left, ascent - m.ascent),
+            cell,
+        ))
+    };
+
+    let (_, encodings) = table(PCF_BDF_ENCODINGS)?.ok_or_else(|| missing("encodings"))?;
+    let (first_byte2, last_byte2) = (encodings.u16(4)?, encodings.u16(6)?);
+    let (first_byte1, last_byte1) = (encodings.u16(8)?, encodings.u16(10)?);
+    let mut glyphs = Vec::new();
+    let mut at = 14;
+
+    for byte1 in first_byte1..=last_byte1 {
+        for byte2 in first_byte2..=last_byte2 {
+            let index = encodings.u16(at)?;
+            at += 2;
+
+            if let Some(c) = char::from_u32(u32::from(byte1) << 8 | u32::from(byte2)) {
+                if index != 0xffff && usize::from(index) < count {
+                    glyphs.push((c, glyph(index.into())?));
+                }
+            }
+        }
+    }
+
+    Ok(BitmapFont::new(
+        pcf_family(table(PCF_PROPERTIES)?.as_ref()),
+        cell,
+        glyphs,
+    ))
+}
+
+/// `FAMILY_NAME` from a PCF properties table
+fn pcf_family(properties: Option<&(u32, Bytes)>) -> Option<String> {
+    let (_, properties) = properties?;
+    let count = properties.usize(4).ok()?;
+    let padding = (4 - count % 4) % 4;
+    let strings = properties.data.get(8 + 9 * count + padding + 4..)?;
+    let string = |offset: i32| {
+        let bytes = strings.get(usize::try_from(offset).ok()?..)?;
+        let end = bytes.iter().position(|b| *b == 0)?;
+        Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    };
+
+    (0..count).find_map(|i| {
+        let at = 8 + 9 * i;
+        let is_string = properties.u8(at + 4).ok()? != 0;
+        (is_string && string(properties.i32(at).ok()?)? == "FAMILY_NAME")
+            .then(|| string(properties.i32(at + 5).ok()?))
+            .flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sorted ink of a glyph, as rows of `#` and `.`
+    fn drawn(font: &BitmapFont, c: char) -> Vec<String> {
+        let (width, height) = font.cell_size();
+        let points = font.glyph(c).cloned().unwrap_or_default();
+
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        let (x, y) = (u16::try_from(x).unwrap(), u16::try_from(y).unwrap());
+                        if points.contains(&(x, y)) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn psf2_with_unicode_table() {
+        let mut data = PSF2_MAGIC.to_vec();
+        for field in [0u32, 32, 1, 2, 3, 3, 4] {
+            data.extend(field.to_le_bytes());
+        }
+        data.extend([0b1001_0000, 0b0110_0000, 0b1111_0000]);
+        data.extend([0b0000_0000, 0b0000_0000, 0b0001_0000]);
+        data.extend("xX".as_bytes());
+        data.extend([0xfe, b'a', 0xff, b'.', 0xff]);
+
+        let font = BitmapFont::parse(&data).unwrap();
+
+        let expected = (
+            (4, 3),
+            vec!['.', 'X', 'x'],
+            vec![
+                String::from("#..#"),
+                String::from(".##."),
+                String::from("####"),
+            ],
+        );
+        let result = (
+            font.cell_size(),
+            font.chars().collect::<Vec<_>>(),
+            drawn(&font, 'X'),
+        );
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn bdf_glyphs_sit_on_the_baseline() {
+        let bdf = "STARTFONT 2.1\n\
+            FONT -misc-tiny-medium-r-normal--4-40-75-75-c-40-iso10646-1\n\
+            FONTBOUNDINGBOX 4 4 0 -1\n\
+            STARTPROPERTIES 1\n\
+            FAMILY_NAME \"Tiny\"\n\
+            ENDPROPERTIES\n\
+            CHARS 1\n\
+            STARTCHAR period\n\
+            ENCODING 46\n\
+            BBX 2 1 1 0\n\
+            BITMAP\n\
+            C0\n\
+            ENDCHAR\n\
+            ENDFONT\n";
+
+        let font = BitmapFont::parse(bdf.as_bytes()).unwrap();
+
+        let expected = (
+            Some("Tiny"),
+            (4, 4),
+            vec![
+                String::from("...."),
+                String::from("...."),
+                String::from(".##."),
+                String::from("...."),
+            ],
+        );
+        let result = (font.name(), font.cell_size(), drawn(&font, '.'));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn malformed_bdf_rows() {
+        let bdf = |row: &str| {
+            format!(
+                "STARTFONT 2.1\n\
+                FONTBOUNDINGBOX 4 1 0 0\n\
+                STARTCHAR x\n\
+                ENCODING 120\n\
+                BITMAP\n\
+                {row}\n\
+                ENDCHAR\n\
+                ENDFONT\n"
+            )
+        };
+
+        let rows = ["0\u{e9}0", "A", "G0"];
+
+        let expected = rows.map(|_| {
+            Err(Error::Invalid(String::from(
+                "bitmap rows must be hexadecimal",
+            )))
+        });
+        let result = rows.map(|row| BitmapFont::parse(bdf(row).as_bytes()).map(|_| ()));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn empty_cells() {
+        let mut psf2 = PSF2_MAGIC.to_vec();
+        for field in [0u32, 32, 0, 1, 0, 8, 0] {
+            psf2.extend(field.to_le_bytes());
+        }
+        let bdf = "STARTFONT 2.1\nFONTBOUNDINGBOX 0 4 0 0\nENDFONT\n";
+
+        let fonts = [&psf2[..], bdf.as_bytes()];
+
+        let expected =
+            fonts.map(|_| Err(Error::Invalid(String::from("glyph cells can't be empty"))));
+        let result = fonts.map(|data| BitmapFont::parse(data).map(|_| ()));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn pcf_with_compressed_metrics() {
+        // metrics, bitmaps and encodings tables, little endian with bits most significant first
+        let metrics = [
+            &PCF_COMPRESSED_METRICS.to_le_bytes()[..],
+            &1u16.to_le_bytes(),
+            &[0x80, 0x83, 0x83, 0x82, 0x80],
+        ]
+        .concat();
+        let bitmaps = [
+            &PCF_BIT_MSB_FIRST.to_le_bytes()[..],
+            &1u32.to_le_bytes(),
+            &0u32.to_le_bytes(),
+            &[0; 16],
+            &[0b1010_0000, 0b0100_0000],
+        ]
+        .concat();
+        let encodings = [
+            &0u32.to_le_bytes()[..],
+            &[b'v', 0, b'v', 0, 0, 0, 0, 0, 0, 0],
+            &0u16.to_le_bytes(),
+        ]
+        .concat();
+
+        let tables = [
+            (PCF_METRICS, metrics),
+            (PCF_BITMAPS, bitmaps),
+            (PCF_BDF_ENCODINGS, encodings),
+        ];
+        let mut data = [&PCF_MAGIC[..], &3u32.to_le_bytes()].concat();
+        let mut offset = 8 + 16 * tables.len();
+        for (kind, table) in &tables {
+            for field in [
+                *kind,
+                0,
+                u32::try_from(table.len()).unwrap(),
+                u32::try_from(offset).unwrap(),
+            ] {
+                data.extend(field.to_le_bytes());
+            }
+            offset += table.len();
+        }
+        for (_, table) in &tables {
+            data.extend(table);
+        }
+
+        let font = BitmapFont::parse(&data).unwrap();
+
+        let expected = ((3, 2), vec![String::from("#.#"), String::from(".#.")]);
+        let result = (font.cell_size(), drawn(&font, 'v'));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use derm_rs::font_utils::xdg_config_home;
+
+use clap::ArgAction;
+use std::path::{Path, PathBuf};
+
+/// Name of the project config file, looked for in the working directory and its ancestors
+const PROJECT_FILE: &str = "derm.toml";
+
+#[derive(Debug)]
+pub enum Error {
+    Read(PathBuf),
+    Parse(PathBuf, String),
+    UnknownProfile(String, Vec<String>),
+    UnknownSetting(String),
+    InvalidValue(String, String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Read(path) => write!(f, "Unable to read {}", path.display()),
+            Error::Parse(path, reason) => write!(f, "Invalid config {}: {reason}", path.display()),
+            Error::UnknownProfile(name, known) if known.is_empty() => {
+                write!(f, "Unknown profile '{name}', no profiles are configured")
+            }
+            Error::UnknownProfile(name, known) => write!(
+                f,
+                "Unknown profile '{name}', expected one of {}",
+                known.join(", ")
+            ),
+            Error::UnknownSetting(key) => write!(f, "Unknown setting '{key}'"),
+            Error::InvalidValue(key, expected) => {
+                write!(f, "Invalid value for '{key}', expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Settings of the `[defaults]` table and of every `[profiles.NAME]` table, keyed by the
+/// long name of the flag they stand for
+#[derive(Default)]
+pub struct Config {
+    defaults: toml::Table,
+    profiles: toml::Table,
+}
+
+impl Config {
+    pub fn parse(config: &str, path: &Path) -> Result<Self, Error> {
+        let parse_error = |reason: String| Error::Parse(path.to_path_buf(), reason);
+        let mut table: toml::Table = config.parse().map_err(|e| parse_error(format!("{e}")))?;
+
+        let mut section = |name: &str| match table.remove(name) {
+            Some(toml::Value::Table(section)) => Ok(section),
+            Some(_) => Err(parse_error(format!("'{name}' isn't a table"))),
+            None => Ok(toml::Table::new()),
+        };
+        let config = Config {
+            defaults: section("defaults")?,
+            profiles: section("profiles")?,
+        };
+
+        if let Some(key) = table.keys().next() {
+            return Err(parse_error(format!(
+                "unexpected '{key}', settings go in [defaults] or [profiles.NAME]"
+            )));
+        }
+        if let Some((name, _)) = config.profiles.iter().find(|(_, p)| !p.is_table()) {
+            return Err(parse_error(format!("profile '{name}' isn't a table")));
+        }
+
+        Ok(config)
+    }
+
+    /// The user config, `$XDG_CONFIG_HOME/derm/config.toml`, overridden by the nearest
+    /// project config
+    pub fn load() -> Result<Self, Error> {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        // without a home to resolve it against the config dir is empty, not the working dir
+        let user = Some(xdg_config_home(home.as_deref()))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| dir.join("derm/config.toml"));
+        let project = std::env::current_dir().ok().and_then(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join(PROJECT_FILE))
+                .find(|path| path.is_file())
+        });
+
+        let mut config = Config::default();
+        for path in user.into_iter().chain(project) {
+            if !path.is_file() {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path).map_err(|_| Error::Read(path.clone()))?;
+            config.merge(Config::parse(&contents, &path)?);
+        }
+
+        Ok(config)
+    }
+
+    /// Overrides settings with those of `other`, profiles setting by setting
+    fn merge(&mut self, other: Config) {
+        self.defaults.extend(other.defaults);
+
+        for (name, profile) in other.profiles {
+            match (self.profiles.get_mut(&name), profile) {
+                (Some(toml::Value::Table(ours)), toml::Value::Table(theirs)) => ours.extend(theirs),
+                (_, profile) => {
+                    self.profiles.insert(name, profile);
+                }
+            }
+        }
+    }
+
+    /// The defaults overridden by the settings of `profile`
+    pub fn settings(&self, profile: Option<&str>) -> Result<toml::Table, Error> {
+        let mut settings = self.defaults.clone();
+
+        if let Some(name) = profile {
+            let Some(toml::Value::Table(profile)) = self.profiles.get(name) else {
+                let known = self.profiles.keys().cloned().collect();
+                return Err(Error::UnknownProfile(name.to_string(), known));
+            };
+            settings.extend(profile.clone());
+        }
+
+        Ok(settings)
+    }
+}
+
+fn scalar(key: &str, value: &toml::Value) -> Result<String, Error> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        _ => Err(Error::InvalidValue(
+            key.to_string(),
+            String::from("a string or number"),
+        )),
+    }
+}
+
+/// Command line arguments for settings, skipping those of flags `given` on the command line
+/// or conflicting with one so that these override the config. Keys are the flags' long
+/// names, with `-` or `_`.
+pub fn args(
+    settings: &toml::Table,
+    command: &clap::Command,
+    given: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, Error> {
+    let mut args = Vec::new();
+
+    for (key, value) in settings {
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .ok_or_else(|| Error::UnknownSetting(key.clone()))?;
+
+        // conflicts are declared on one side only, so both sides are checked
+        let conflicts = |a: &clap::Arg, b: &clap::Arg| {
+            command
+                .get_arg_conflicts_with(a)
+                .iter()
+                .any(|c| c.get_id() == b.get_id())
+        };
+        let overridden = command
+            .get_arguments()
+            .filter(|other| given(other.get_id().as_str()))
+            .any(|other| {
+                other.get_id() == arg.get_id() || conflicts(arg, other) || conflicts(other, arg)
+            });
+        if overridden {
+            continue;
+        }
+
+        let flag = format!("--{long}");
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, toml::Value::Boolean(set)) => {
+                args.extend(set.then_some(flag));
+            }
+            (ArgAction::SetTrue, _) => {
+                return Err(Error::InvalidValue(
+                    key.clone(),
+                    String::from("true or false"),
+                ));
+            }
+            (ArgAction::Count, toml::Value::Integer(count)) => {
+                let count = usize::try_from(*count)
+                    .map_err(|_| Error::InvalidValue(key.clone(), String::from("a count")))?;
+                args.extend(std::iter::repeat_n(flag, count));
+            }
+            (ArgAction::Count, _) => {
+                return Err(Error::InvalidValue(key.clone(), String::from("a count")));
+            }
+            (_, toml::Value::Array(values)) => {
+                for value in values {
+                    args.push(format!("{flag}={}", scalar(key, value)?));
+                }
+            }
+            (_, value) => args.push(format!("{flag}={}", scalar(key, value)?)),
+        }
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    const CONFIG: &str = "[defaults]\n\
+        font = ['Hack', 'Noto Sans Symbols']\n\
+        pixels-per-char = 20\n\n\
+        [profiles.docs]\n\
+        format = 'svg'\n\
+        outline_glyphs = true\n\
+        pixels-per-char = 12\n";
+
+    fn command() -> clap::Command {
+        clap::Command::new("derm")
+            .arg(Arg::new("font").long("font").action(ArgAction::Append))
+            .arg(Arg::new("pixels_per_char").long("pixels-per-char"))
+            .arg(Arg::new("format").long("format"))
+            .arg(
+                Arg::new("outline_glyphs")
+                    .long("outline-glyphs")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(Arg::new("output").long("output"))
+            .arg(
+                Arg::new("output_dir")
+                    .long("output-dir")
+                    .conflicts_with("output"),
+            )
+            .arg(Arg::new("quiet").long("quiet").action(ArgAction::SetTrue))
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .action(ArgAction::Count)
+                    .conflicts_with("quiet"),
+            )
+    }
+
+    #[test]
+    fn profile_overrides_defaults() {
+        let config = Config::parse(CONFIG, Path::new("derm.toml")).unwrap();
+        let settings = config.settings(Some("docs")).unwrap();
+
+        let expected = vec![
+            "--font=Hack",
+            "--font=Noto Sans Symbols",
+            "--format=svg",
+            "--outline-glyphs",
+            "--pixels-per-char=12",
+        ];
+        let result = args(&settings, &command(), |_| false).unwrap();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let mut config = Config::parse(CONFIG, Path::new("config.toml")).unwrap();
+        let project = "[profiles.docs]\nformat = 'png'\n";
+        config.merge(Config::parse(project, Path::new("derm.toml")).unwrap());
+        let settings = config.settings(Some("docs")).unwrap();
+
+        let expected = vec!["--format=png", "--outline-glyphs"];
+        let result = args(&settings, &command(), |id| {
+            id == "font" || id == "pixels_per_char"
+        })
+        .unwrap();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_conflicting_flags() {
+        let config = "[defaults]\nquiet = true\n[profiles.verbose]\nverbose = 2\n";
+        let config = Config::parse(config, Path::new("derm.toml")).unwrap();
+
+        let expected = vec![Vec::<String>::new(), Vec::new()];
+        let result = vec![
+            args(&config.settings(None).unwrap(), &command(), |id| {
+                id == "verbose"
+            })
+            .unwrap(),
+            args(
+                &config.settings(Some("verbose")).unwrap(),
+                &command(),
+                |id| id == "quiet",
+            )
+            .unwrap(),
+        ];
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_conflicting_values() {
+        let config = "[defaults]\noutput = '/tmp/x.txt'\nformat = 'svg'\n";
+        let config = Config::parse(config, Path::new("derm.toml")).unwrap();
+        let settings = config.settings(None).unwrap();
+
+        let expected = vec!["--format=svg"];
+        let result = args(&settings, &command(), |id| id == "output_dir").unwrap();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn negative_counts_are_invalid() {
+        let config = Config::parse("[defaults]\nverbose = -1\n", Path::new("derm.toml")).unwrap();
+        let settings = config.settings(None).unwrap();
+
+        let result = args(&settings, &command(), |_| false);
+
+        assert!(
+            matches!(&result, Err(Error::InvalidValue(key, _)) if key == "verbose"),
+            "Expected an invalid verbose count, but got: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::visualize::compare_cell;
+use derm_rs::render::{Cell, Grid, Prepared, Renderer};
+use derm_rs::similarity::Points;
+
+use crossterm::style::Stylize;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Shades from best to worst match
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+/// Parses a cell given as `ROW,COLUMN`, both counted from zero
+pub fn parse_cell(s: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("Invalid cell '{s}', expected ROW,COLUMN");
+    let (row, column) = s.split_once(',').ok_or_else(invalid)?;
+
+    Ok((
+        row.trim().parse().map_err(|_| invalid())?,
+        column.trim().parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// Cells from `first` to `last` inclusive, rows and columns alike
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellRange {
+    first: (usize, usize),
+    last: (usize, usize),
+}
+
+/// Parses a single cell as `ROW,COLUMN` or a rectangle of them as `ROW,COLUMN:ROW,COLUMN`
+pub fn parse_cell_range(s: &str) -> Result<CellRange, String> {
+    let (first, last) = match s.split_once(':') {
+        Some((first, last)) => (parse_cell(first)?, parse_cell(last)?),
+        None => (parse_cell(s)?, parse_cell(s)?),
+    };
+
+    Ok(CellRange {
+        first: (first.0.min(last.0), first.1.min(last.1)),
+        last: (first.0.max(last.0), first.1.max(last.1)),
+    })
+}
+
+/// Every cell of a range within a grid shown next to the glyph it matched, see
+/// [`compare_cell`]
+pub fn compare_cells(
+    renderer: &Renderer,
+    prepared: &Prepared,
+    grid: &Grid,
+    range: CellRange,
+) -> Vec<String> {
+    let size = renderer.cell_size();
+    let empty = Points::new();
+    let mut lines = Vec::new();
+
+    for (row, cells) in grid
+        .rows()
+        .enumerate()
+        .take(range.last.0 + 1)
+        .skip(range.first.0)
+    {
+        for (column, cell) in cells
+            .iter()
+            .enumerate()
+            .take(range.last.1 + 1)
+            .skip(range.first.1)
+        {
+            let (Ok(y), Ok(x)) = (u32::try_from(row), u32::try_from(column)) else {
+                continue;
+            };
+            let Some(partition) = renderer.cell_points(prepared, y, x) else {
+                continue;
+            };
+            let glyph = renderer.glyph_points(cell.glyph).unwrap_or(&empty);
+
+            lines.push(describe(row, column, cell));
+            lines.extend(compare_cell(size, &partition, glyph));
+        }
+    }
+
+    lines
+}
+
+/// The `n` scored cells with the highest error as `(row, column, cell)`, worst first
+fn worst_cells<'a>(
+    rows: impl Iterator<Item = &'a [Cell]>,
+    n: usize,
+) -> Vec<(usize, usize, &'a Cell)> {
+    let mut scored: Vec<_> = rows
+        .enumerate()
+        .flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .map(move |(column, cell)| (row, column, cell))
+        })
+        .filter(|(_, _, cell)| cell.score.is_some())
+        .collect();
+
+    let score = |cell: &Cell| cell.score.unwrap_or_default();
+    scored.sort_by(|(_, _, a), (_, _, b)| score(b).total_cmp(&score(a)));
+    scored.truncate(n);
+    scored
+}
+
+/// Shades every cell by its error relative to the rest of the grid, two columns per cell
+/// like the glyphs themselves. Cells without a score are left blank and the `worst` cells
+/// are drawn in red, or as `!!` when `color` is off.
+fn heatmap<'a>(
+    rows: impl Iterator<Item = &'a [Cell]> + Clone,
+    worst: &[(usize, usize, &Cell)],
+    color: bool,
+) -> Vec<String> {
+    let (min, max) = rows
+        .clone()
+        .flatten()
+        .filter_map(|cell| cell.score)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), s| {
+            (min.min(s), max.max(s))
+        });
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let shade = |score: f64| {
+        let level = if max > min {
+            ((score - min) / (max - min) * (SHADES.len() - 1) as f64).round() as usize
+        } else {
+            0
+        };
+        SHADES[level.min(SHADES.len() - 1)]
+    };
+
+    rows.enumerate()
+        .map(|(row, cells)| {
+            let mut line = String::new();
+
+            for (column, cell) in cells.iter().enumerate() {
+                let is_worst = worst.iter().any(|&(r, c, _)| (r, c) == (row, column));
+
+                match cell.score {
+                    Some(_) if is_worst && color => {
+                        let _ = write!(line, "{}", "██".red());
+                    }
+                    Some(_) if is_worst => line.push_str("!!"),
+                    Some(score) => line.extend([shade(score); 2]),
+                    None => line.push_str("  "),
+                }
+            }
+
+            line
+        })
+        .collect()
+}
+
+fn describe(row: usize, column: usize, cell: &Cell) -> String {
+    let mut description = format!("cell {row},{column}: '{}'", cell.glyph);
+
+    match cell.score {
+        Some(score) => {
+            let _ = write!(description, " score {score:.3}");
+        }
+        None => description.push_str(" unscored"),
+    }
+
+    for candidate in &cell.candidates {
+        let _ = write!(
+            description,
+            "\n  '{}' score {:.3}",
+            candidate.glyph, candidate.score
+        );
+    }
+
+    description
+}
+
+/// What to report about a rendered grid
+pub struct Diagnostics {
+    /// Print the heatmap and list the `worst` cells
+    pub heatmap: bool,
+    pub worst: usize,
+
+    /// Cells whose alternatives are listed
+    pub inspect: Vec<(usize, usize)>,
+
+    /// Highlight with terminal colors
+    pub color: bool,
+}
+
+pub fn write(grid: &Grid, diagnostics: &Diagnostics, out: &mut impl Write) -> io::Result<()> {
+    if diagnostics.heatmap {
+        let worst = worst_cells(grid.rows(), diagnostics.worst);
+
+        writeln!(
+            out,
+            "match error, {} best to {} worst",
+            SHADES[0],
+            SHADES[SHADES.len() - 1]
+        )?;
+        for line in heatmap(grid.rows(), &worst, diagnostics.color) {
+            writeln!(out, "{line}")?;
+        }
+
+        writeln!(out, "\nworst cells:")?;
+        for &(row, column, cell) in &worst {
+            writeln!(out, "{}", describe(row, column, cell))?;
+        }
+    }
+
+    for &(row, column) in &diagnostics.inspect {
+        if let Some(cell) = grid.rows().nth(row).and_then(|cells| cells.get(column)) {
+            writeln!(out, "{}", describe(row, column, cell))?;
+        } else {
+            let (columns, rows) = grid.dimensions();
+            writeln!(
+                out,
+                "cell {row},{column}: outside of the {columns}x{rows} grid"
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derm_rs::render::Candidate;
+    use image::Rgb;
+
+    fn cell(score: Option<f64>) -> Cell {
+        Cell {
+            glyph: 'a',
+            color: Rgb([0, 0, 0]),
+            score,
+            candidates: vec![Candidate {
+                glyph: 'b',
+                score: 1.0,
+            }],
+            error: None,
+        }
+    }
+
+    #[test]
+    fn parse_row_and_column() {
+        let expected = Ok((3, 12));
+        let result = parse_cell("3, 12");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_range_normalizes_corners() {
+        let expected = Ok(CellRange {
+            first: (1, 2),
+            last: (4, 6),
+        });
+        let result = parse_cell_range("4,2:1,6");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn worst_cells_ordered() {
+        let cells = [
+            cell(Some(1.0)),
+            cell(None),
+            cell(Some(5.0)),
+            cell(Some(3.0)),
+        ];
+
+        let expected = vec![(1, 0, 5.0), (1, 1, 3.0)];
+        let result: Vec<_> = worst_cells(cells.chunks(2), 2)
+            .into_iter()
+            .map(|(row, column, cell)| (row, column, cell.score.unwrap()))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn heatmap_shades() {
+        let cells = [
+            cell(Some(0.0)),
+            cell(None),
+            cell(Some(3.0)),
+            cell(Some(6.0)),
+        ];
+        let worst = worst_cells(cells.chunks(2), 1);
+
+        let expected = vec!["░░  ", "▓▓!!"];
+        let result = heatmap(cells.chunks(2), &worst, false);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
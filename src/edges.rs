@@ -0,0 +1,373 @@
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Pixel};
+
+/// Minimum coherence of a cell's gradients for it to be considered oriented
+const MIN_COHERENCE: f32 = 0.6;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeDetector {
+    /// Thresholded Sobel gradient magnitude
+    Sobel,
+
+    /// Canny edge detector, thin edges with hysteresis
+    Canny,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal,
+}
+
+impl Orientation {
+    /// Full width line glyph for the orientation, matching the width of the candidate glyphs
+    #[must_use]
+    pub fn glyph(self) -> char {
+        match self {
+            Orientation::Horizontal => '－',
+            Orientation::Vertical => '｜',
+            Orientation::Diagonal => '／',
+            Orientation::AntiDiagonal => '＼',
+        }
+    }
+}
+
+/// Sobel gradients `(gx, gy)` for every pixel, row major, with intensities scaled to `[0, 1]`
+fn gradient_iter<I>(img: &I) -> impl Iterator<Item = (f32, f32)> + '_
+where
+    I: GenericImageView,
+    I::Pixel: Pixel<Subpixel = u8>,
+{
+    let (width, height) = img.dimensions();
+    // an empty image has no pixels to clamp to
+    let height = if width == 0 { 0 } else { height };
+
+    let luma = move |x: i64, y: i64| -> f32 {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (x, y) = (
+            x.clamp(0, i64::from(width) - 1) as u32,
+            y.clamp(0, i64::from(height) - 1) as u32,
+        );
+        // transparent pixels are background, which is white
+        let pixel = img.get_pixel(x, y).to_rgba();
+        let alpha = f32::from(pixel[3]) / 255.0;
+        f32::from(pixel.to_luma().0[0]) / 255.0 * alpha + (1.0 - alpha)
+    };
+
+    (0..height)
+        .flat_map(move |y| (0..width).map(move |x| (i64::from(x), i64::from(y))))
+        .map(move |(x, y)| {
+            let gx = (luma(x + 1, y - 1) + 2.0 * luma(x + 1, y) + luma(x + 1, y + 1))
+                - (luma(x - 1, y - 1) + 2.0 * luma(x - 1, y) + luma(x - 1, y + 1));
+            let gy = (luma(x - 1, y + 1) + 2.0 * luma(x, y + 1) + luma(x + 1, y + 1))
+                - (luma(x - 1, y - 1) + 2.0 * luma(x, y - 1) + luma(x + 1, y - 1));
+
+            (gx / 4.0, gy / 4.0)
+        })
+}
+
+fn gradients<I>(img: &I) -> Vec<(f32, f32)>
+where
+    I: GenericImageView,
+    I::Pixel: Pixel<Subpixel = u8>,
+{
+    gradient_iter(img).collect()
+}
+
+fn non_maximum_suppression(gradients: &[(f32, f32)], width: usize, height: usize) -> Vec<f32> {
+    let magnitude = |i: usize| gradients[i].0.hypot(gradients[i].1);
+
+    (0..gradients.len())
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let (gx, gy) = gradients[i];
+            let angle = gy.atan2(gx).to_degrees().rem_euclid(180.0);
+
+            let (dx, dy): (isize, isize) = match angle {
+                a if !(22.5..157.5).contains(&a) => (1, 0),
+                a if a < 67.5 => (1, 1),
+                a if a < 112.5 => (0, 1),
+                _ => (-1, 1),
+            };
+
+            let neighbour = |sign: isize| {
+                let nx = x.checked_add_signed(sign * dx).filter(|&nx| nx < width);
+                let ny = y.checked_add_signed(sign * dy).filter(|&ny| ny < height);
+                match (nx, ny) {
+                    (Some(nx), Some(ny)) => magnitude(ny * width + nx),
+                    _ => 0.0,
+                }
+            };
+
+            let m = magnitude(i);
+            if m >= neighbour(1) && m >= neighbour(-1) {
+                m
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+fn hysteresis(magnitudes: &[f32], width: usize, height: usize, low: f32, high: f32) -> Vec<bool> {
+    let mut edges = vec![false; magnitudes.len()];
+    let mut stack: Vec<usize> = (0..magnitudes.len())
+        .filter(|&i| magnitudes[i] >= high)
+        .collect();
+
+    while let Some(i) = stack.pop() {
+        if edges[i] {
+            continue;
+        }
+        edges[i] = true;
+
+        let (x, y) = (i % width, i / width);
+        for (dx, dy) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ] {
+            let nx = x.checked_add_signed(dx).filter(|&nx| nx < width);
+            let ny = y.checked_add_signed(dy).filter(|&ny| ny < height);
+
+            if let (Some(nx), Some(ny)) = (nx, ny) {
+                let n = ny * width + nx;
+                if !edges[n] && magnitudes[n] >= low {
+                    stack.push(n);
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+/// Converts an image into a line drawing, edges are black on a white background
+#[must_use]
+pub fn detect(img: &DynamicImage, detector: EdgeDetector, threshold: f32) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let gradients = gradients(img);
+
+    let edges: Vec<bool> = match detector {
+        EdgeDetector::Sobel => gradients
+            .iter()
+            .map(|(gx, gy)| gx.hypot(*gy) >= threshold)
+            .collect(),
+        EdgeDetector::Canny => {
+            let (width, height) = (width as usize, height as usize);
+            let blurred = img.blur(1.4);
+            let magnitudes = non_maximum_suppression(&self::gradients(&blurred), width, height);
+            hysteresis(&magnitudes, width, height, threshold * 0.4, threshold)
+        }
+    };
+
+    DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+        if edges[(y * width + x) as usize] {
+            Luma([0])
+        } else {
+            Luma([255])
+        }
+    }))
+}
+
+/// Line drawing of a grayscale image written into `out`, reusing its buffer and that of
+/// `gradients`. Canny's blurred image and intermediate maps are still allocated.
+pub fn detect_into(
+    img: &GrayImage,
+    detector: EdgeDetector,
+    threshold: f32,
+    gradients: &mut Vec<(f32, f32)>,
+    out: &mut GrayImage,
+) {
+    let (width, height) = img.dimensions();
+    if out.dimensions() != (width, height) {
+        *out = GrayImage::new(width, height);
+    }
+    let edge = |is_edge: bool| if is_edge { 0 } else { 255 };
+
+    match detector {
+        EdgeDetector::Sobel => {
+            gradients.clear();
+            gradients.extend(gradient_iter(img));
+            for (p, (gx, gy)) in out.iter_mut().zip(gradients.iter()) {
+                *p = edge(gx.hypot(*gy) >= threshold);
+            }
+        }
+        EdgeDetector::Canny => {
+            let (width, height) = (width as usize, height as usize);
+            let blurred = image::imageops::blur(img, 1.4);
+            gradients.clear();
+            gradients.extend(gradient_iter(&blurred));
+            let magnitudes = non_maximum_suppression(gradients, width, height);
+            let edges = hysteresis(&magnitudes, width, height, threshold * 0.4, threshold);
+            for (p, is_edge) in out.iter_mut().zip(edges) {
+                *p = edge(is_edge);
+            }
+        }
+    }
+}
+
+/// Dominant line orientation of an image using the structure tensor of its gradients,
+/// `None` when the gradients don't agree on a direction
+pub fn dominant_orientation<I>(img: &I) -> Option<Orientation>
+where
+    I: GenericImageView,
+    I::Pixel: Pixel<Subpixel = u8>,
+{
+    let (jxx, jyy, jxy) = gradient_iter(img).fold((0.0, 0.0, 0.0), |(xx, yy, xy), (gx, gy)| {
+        (xx + gx * gx, yy + gy * gy, xy + gx * gy)
+    });
+
+    let energy = jxx + jyy;
+    if energy <= f32::EPSILON {
+        return None;
+    }
+
+    let coherence = (jxx - jyy).hypot(2.0 * jxy) / energy;
+    if coherence < MIN_COHERENCE {
+        return None;
+    }
+
+    // direction of the gradient, perpendicular to the line, with y pointing down
+    let angle = (0.5 * (2.0 * jxy).atan2(jxx - jyy)).to_degrees();
+
+    Some(match angle {
+        a if a.abs() < 22.5 => Orientation::Vertical,
+        a if a.abs() > 67.5 => Orientation::Horizontal,
+        a if a > 0.0 => Orientation::Diagonal,
+        _ => Orientation::AntiDiagonal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_image(on_line: fn(i32, i32) -> bool) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(16, 16, |x, y| {
+            #[allow(clippy::cast_possible_wrap)]
+            if on_line(x as i32, y as i32) {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        }))
+    }
+
+    #[test]
+    fn horizontal_line() {
+        let img = line_image(|_, y| (7..=8).contains(&y));
+
+        let expected = Some(Orientation::Horizontal);
+        let result = dominant_orientation(&img);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn vertical_line() {
+        let img = line_image(|x, _| (7..=8).contains(&x));
+
+        let expected = Some(Orientation::Vertical);
+        let result = dominant_orientation(&img);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn diagonal_line() {
+        let img = line_image(|x, y| (x + y - 15).abs() <= 1);
+
+        let expected = Some(Orientation::Diagonal);
+        let result = dominant_orientation(&img);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn anti_diagonal_line() {
+        let img = line_image(|x, y| (x - y).abs() <= 1);
+
+        let expected = Some(Orientation::AntiDiagonal);
+        let result = dominant_orientation(&img);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn blank_image_has_no_orientation() {
+        let img = line_image(|_, _| false);
+
+        let expected = None;
+        let result = dominant_orientation(&img);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn edges_into_reused_buffer_match() {
+        let img = line_image(|x, y| x + y < 16);
+        let mut gradients = Vec::new();
+        let mut out = GrayImage::new(1, 1);
+
+        let expected: Vec<_> = [EdgeDetector::Sobel, EdgeDetector::Canny]
+            .into_iter()
+            .map(|detector| detect(&img, detector, 0.25).to_luma8())
+            .collect();
+        let result: Vec<_> = [EdgeDetector::Sobel, EdgeDetector::Canny]
+            .into_iter()
+            .map(|detector| {
+                detect_into(&img.to_luma8(), detector, 0.25, &mut gradients, &mut out);
+                out.clone()
+            })
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn sobel_step_edge() {
+        let img = line_image(|x, _| x < 8);
+
+        let expected = vec![
+            255, 255, 255, 255, 255, 255, 255, 0, 0, 255, 255, 255, 255, 255, 255, 255,
+        ];
+        let result: Vec<u8> = detect(&img, EdgeDetector::Sobel, 0.25)
+            .to_luma8()
+            .rows()
+            .next()
+            .unwrap()
+            .map(|p| p.0[0])
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
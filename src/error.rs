@@ -0,0 +1,169 @@
+use crate::{image_utils, preprocess};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What happens when a cell can't be matched to any glyph
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPolicy {
+    /// Stop at the first cell that can't be matched
+    Fail,
+
+    /// Use the fallback character and warn how many cells needed it
+    #[default]
+    Warn,
+
+    /// Quietly use the fallback character
+    Substitute,
+}
+
+/// Why a single cell couldn't be matched
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellError {
+    /// The cell is too large for its pixels to be stored as points
+    Coordinates,
+
+    /// Comparing the cell failed against every glyph, with the first failure
+    Unmatched(String),
+}
+
+impl std::fmt::Display for CellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellError::Coordinates => write!(f, "Cell too large for point coordinates"),
+            CellError::Unmatched(reason) => write!(f, "No glyph could be compared: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CellError {}
+
+/// Cells that fell back to the fallback character, counted by reason
+#[derive(Default)]
+pub struct Fallbacks {
+    counts: Vec<(String, usize)>,
+}
+
+impl Fallbacks {
+    pub fn add<'a>(&mut self, errors: impl Iterator<Item = &'a CellError>) {
+        for error in errors {
+            let reason = error.to_string();
+            match self.counts.iter_mut().find(|(r, _)| *r == reason) {
+                Some((_, count)) => *count += 1,
+                None => self.counts.push((reason, 1)),
+            }
+        }
+    }
+
+    /// Reasons and their counts, most common first
+    #[must_use]
+    pub fn summary(&self) -> Vec<(String, usize)> {
+        let mut counts = self.counts.clone();
+        counts.sort_by(|(a, i), (b, j)| j.cmp(i).then(a.cmp(b)));
+        counts
+    }
+
+    /// Logs how many cells fell back and why, as warnings under [`ErrorPolicy::Warn`]
+    pub fn report(&self, policy: ErrorPolicy, fallback: char) {
+        for (reason, count) in self.summary() {
+            if policy == ErrorPolicy::Warn {
+                tracing::warn!(count, "{count} cells fell back to {fallback:?}: {reason}");
+            } else {
+                tracing::info!(count, "{count} cells fell back to {fallback:?}: {reason}");
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    FontNotFound(String),
+    InvalidFont(String, String),
+    Open(String),
+    Decode(image::ImageError),
+    Image(image_utils::Error),
+    Io(std::io::Error),
+    Preprocess(preprocess::Error),
+    Cell {
+        row: usize,
+        column: usize,
+        error: CellError,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::FontNotFound(font) => write!(f, "Font '{font}' not found"),
+            Error::InvalidFont(font, reason) => write!(f, "Unable to load font '{font}': {reason}"),
+            Error::Open(path) => write!(f, "Unable to open {path}"),
+            Error::Decode(e) => write!(f, "Unable to decode image: {e}"),
+            Error::Image(e) => write!(f, "{e}"),
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Preprocess(e) => write!(f, "{e}"),
+            Error::Cell { row, column, error } => write!(f, "Cell {row},{column}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Decode(e) => Some(e),
+            Error::Image(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Preprocess(e) => Some(e),
+            Error::Cell { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+impl From<image_utils::Error> for Error {
+    fn from(e: image_utils::Error) -> Self {
+        Error::Image(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<preprocess::Error> for Error {
+    fn from(e: preprocess::Error) -> Self {
+        Error::Preprocess(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_reasons() {
+        let unmatched = CellError::Unmatched(String::from("empty set"));
+        let errors = [unmatched.clone(), CellError::Coordinates, unmatched.clone()];
+
+        let mut fallbacks = Fallbacks::default();
+        fallbacks.add(errors.iter());
+
+        let expected = vec![
+            (unmatched.to_string(), 2),
+            (CellError::Coordinates.to_string(), 1),
+        ];
+        let result = fallbacks.summary();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -1,11 +1,553 @@
+use crate::bitmap_font::BitmapFont;
+use crate::error::{Error, Result};
+use crate::glyphs::GlyphSource;
+
 use fontdue::Font;
-use std::error::Error;
-
-// TODO: make this more inteligent
-pub fn search_for_font(path: &str) -> Result<Font, Box<dyn Error>> {
-    let font_data = std::fs::read(path).map_err(|_| format!("font \'{}\' not found", &path))?;
-    Ok(Font::from_bytes(
-        font_data,
-        fontdue::FontSettings::default(),
-    )?)
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Families tried in order for the generic aliases, before any other font of that kind
+const MONOSPACE: [&str; 7] = [
+    "DejaVu Sans Mono",
+    "Noto Sans Mono",
+    "Liberation Mono",
+    "Ubuntu Mono",
+    "Source Code Pro",
+    "Hack",
+    "Menlo",
+];
+const SANS_SERIF: [&str; 4] = ["DejaVu Sans", "Noto Sans", "Liberation Sans", "Ubuntu"];
+const SERIF: [&str; 3] = ["DejaVu Serif", "Noto Serif", "Liberation Serif"];
+
+/// Styles of a family's default face
+const REGULAR_STYLES: [&str; 4] = ["regular", "book", "normal", "roman"];
+
+const FONT_EXTENSIONS: [&str; 8] = ["ttf", "otf", "ttc", "otc", "pcf", "bdf", "psf", "psfu"];
+
+/// Directories of the Linux console's fonts, searched along with the font directories
+const CONSOLE_FONT_DIRS: [&str; 3] = [
+    "/usr/share/consolefonts",
+    "/usr/share/kbd/consolefonts",
+    "/usr/lib/kbd/consolefonts",
+];
+
+/// A face found while scanning the system's font directories
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontFace {
+    pub path: PathBuf,
+
+    /// Position of the face within a collection, 0 for single fonts
+    pub index: u32,
+    pub family: String,
+    pub style: String,
+    pub full_name: String,
+    pub monospaced: bool,
+}
+
+impl FontFace {
+    fn parse(path: &Path, data: &[u8], index: u32) -> Option<Self> {
+        let face = ttf_parser::Face::parse(data, index).ok()?;
+        let name = |ids: &[u16]| {
+            ids.iter().find_map(|id| {
+                face.names()
+                    .into_iter()
+                    .filter(|n| n.name_id == *id && n.is_unicode())
+                    .find_map(|n| n.to_string())
+            })
+        };
+
+        let family = name(&[
+            ttf_parser::name_id::TYPOGRAPHIC_FAMILY,
+            ttf_parser::name_id::FAMILY,
+        ])?;
+        let style = name(&[
+            ttf_parser::name_id::TYPOGRAPHIC_SUBFAMILY,
+            ttf_parser::name_id::SUBFAMILY,
+        ])
+        .unwrap_or_else(|| String::from("Regular"));
+        let full_name =
+            name(&[ttf_parser::name_id::FULL_NAME]).unwrap_or_else(|| format!("{family} {style}"));
+
+        Some(FontFace {
+            path: path.to_path_buf(),
+            index,
+            family,
+            style,
+            full_name,
+            monospaced: face.is_monospaced(),
+        })
+    }
+
+    fn is_regular(&self) -> bool {
+        REGULAR_STYLES.contains(&normalize(&self.style).as_str())
+    }
+
+    /// A bitmap font, named after its file when it doesn't carry a name
+    fn bitmap(path: &Path, data: &[u8]) -> Option<Self> {
+        let font = BitmapFont::parse(data).ok()?.or_named(&file_stem(path));
+        let family = font.name()?.to_string();
+
+        Some(FontFace {
+            path: path.to_path_buf(),
+            index: 0,
+            full_name: family.clone(),
+            family,
+            style: String::from("Regular"),
+            monospaced: true,
+        })
+    }
+
+    /// Every face of a font file, several for `.ttc` and `.otc` collections
+    fn all_in(path: &Path, data: &[u8]) -> Vec<Self> {
+        if BitmapFont::is_bitmap(data) {
+            return FontFace::bitmap(path, data).into_iter().collect();
+        }
+
+        (0..ttf_parser::fonts_in_collection(data).unwrap_or(1))
+            .filter_map(|index| FontFace::parse(path, data, index))
+            .collect()
+    }
+}
+
+/// Which face of a collection to load
+#[derive(Clone, Debug, PartialEq)]
+pub enum FaceSelector {
+    Index(u32),
+
+    /// Family, full name or style of the face
+    Name(String),
+}
+
+/// A face index, anything else selects a face by name
+impl std::str::FromStr for FaceSelector {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(s.trim().parse().map_or_else(
+            |_| FaceSelector::Name(s.trim().to_string()),
+            FaceSelector::Index,
+        ))
+    }
+}
+
+/// A loaded face along with the file it came from
+pub struct LoadedFont {
+    pub font: GlyphSource,
+    pub data: Vec<u8>,
+    pub index: u32,
+}
+
+/// Lowercase without spaces, dashes or underscores, so `DejaVu Sans-Mono` matches
+/// `dejavusansmono`
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// File name without its extensions, `Lat2-Terminus16` for `Lat2-Terminus16.psf.gz`
+fn file_stem(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    Path::new(name)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// `<dir>` entries of a fontconfig configuration file, relative `prefix="xdg"` entries are
+/// resolved against `xdg_data_home` and a leading `~` against `home`
+#[must_use]
+pub fn fontconfig_dirs(config: &str, home: Option<&Path>, xdg_data_home: &Path) -> Vec<PathBuf> {
+    config
+        .split("<dir")
+        .skip(1)
+        .filter_map(|entry| {
+            let (attributes, rest) = entry.split_once('>')?;
+            let (dir, _) = rest.split_once("</dir>")?;
+            let dir = dir.trim();
+
+            if attributes.contains("prefix=\"xdg\"") {
+                Some(xdg_data_home.join(dir))
+            } else if let Some(relative) = dir.strip_prefix('~') {
+                Some(home?.join(relative.trim_start_matches('/')))
+            } else {
+                Some(PathBuf::from(dir))
+            }
+        })
+        .collect()
+}
+
+/// Standard font directories followed by those named in fontconfig's configuration
+fn font_dirs() -> Vec<PathBuf> {
+    let home = home();
+    let xdg_data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".local/share")))
+        .unwrap_or_default();
+    let xdg_data_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| String::from("/usr/local/share:/usr/share"));
+
+    let mut dirs = vec![xdg_data_home.join("fonts")];
+    dirs.extend(home.iter().map(|h| h.join(".fonts")));
+    dirs.extend(
+        xdg_data_dirs
+            .split(':')
+            .filter(|d| !d.is_empty())
+            .map(|d| Path::new(d).join("fonts")),
+    );
+
+    dirs.extend(CONSOLE_FONT_DIRS.iter().map(PathBuf::from));
+
+    let mut configs = vec![PathBuf::from("/etc/fonts/fonts.conf")];
+    if let Ok(entries) = std::fs::read_dir("/etc/fonts/conf.d") {
+        let mut conf_d: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+        conf_d.sort();
+        configs.extend(conf_d);
+    }
+    configs.push(xdg_config_home(home.as_deref()).join("fontconfig/fonts.conf"));
+
+    for config in configs {
+        if let Ok(contents) = std::fs::read_to_string(&config) {
+            dirs.extend(fontconfig_dirs(&contents, home.as_deref(), &xdg_data_home));
+        }
+    }
+
+    let mut unique = Vec::new();
+    for dir in dirs {
+        if !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+    unique
+}
+
+pub fn xdg_config_home(home: Option<&Path>) -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|h| h.join(".config")))
+        .unwrap_or_default()
+}
+
+/// Font files under `dir`, following symlinks but entering each directory once so that
+/// symlink cycles end
+fn font_files(dir: &Path, files: &mut Vec<PathBuf>, visited: &mut HashSet<PathBuf>) {
+    let Ok(canonical) = dir.canonicalize() else {
+        return;
+    };
+    if !visited.insert(canonical) {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            font_files(&path, files, visited);
+        } else if Path::new(path.to_string_lossy().trim_end_matches(".gz"))
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| FONT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Every face in the system's font directories, ordered by family and style. The
+/// directories are scanned on the first call only.
+#[must_use]
+pub fn system_fonts() -> &'static [FontFace] {
+    static FACES: OnceLock<Vec<FontFace>> = OnceLock::new();
+    FACES.get_or_init(scan_system_fonts)
+}
+
+fn scan_system_fonts() -> Vec<FontFace> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    for dir in font_dirs() {
+        font_files(&dir, &mut files, &mut visited);
+    }
+    files.sort();
+    files.dedup();
+
+    let mut faces: Vec<FontFace> = files
+        .iter()
+        .filter_map(|path| Some(FontFace::all_in(path, &std::fs::read(path).ok()?)))
+        .flatten()
+        .collect();
+    faces.sort_by(|a, b| {
+        (&a.family, &a.style, &a.path, a.index).cmp(&(&b.family, &b.style, &b.path, b.index))
+    });
+    faces
+}
+
+/// Faces matching a family, full name or generic alias (`mono`, `monospace`, `sans`,
+/// `sans-serif`, `serif`), best match first
+#[must_use]
+pub fn matching<'a>(query: &str, faces: &'a [FontFace]) -> Vec<&'a FontFace> {
+    let query = normalize(query);
+    let preferred = |families: &[&str], face: &FontFace| {
+        families
+            .iter()
+            .position(|f| normalize(f) == normalize(&face.family))
+            .unwrap_or(families.len())
+    };
+
+    // exact names first, then families, then aliases, regular styles before others
+    let rank = |face: &FontFace| -> Option<(usize, bool)> {
+        let family = normalize(&face.family);
+        let style = normalize(&face.style);
+        let other_style = !face.is_regular();
+
+        let sans = !face.monospaced && (!family.contains("serif") || family.contains("sans"));
+        let serif = family.contains("serif") && !family.contains("sans");
+
+        match query.as_str() {
+            _ if normalize(&face.full_name) == query || family.clone() + &style == query => {
+                Some((0, false))
+            }
+            _ if family == query => Some((1, other_style)),
+            "mono" | "monospace" if face.monospaced => {
+                Some((2 + preferred(&MONOSPACE, face), other_style))
+            }
+            "sans" | "sansserif" if sans => Some((2 + preferred(&SANS_SERIF, face), other_style)),
+            "serif" if serif => Some((2 + preferred(&SERIF, face), other_style)),
+            _ => None,
+        }
+    };
+
+    let mut ranked: Vec<_> = faces
+        .iter()
+        .filter_map(|face| rank(face).map(|r| (r, face)))
+        .collect();
+    ranked.sort_by_key(|(r, _)| *r);
+    ranked.into_iter().map(|(_, face)| face).collect()
+}
+
+/// The first of `faces` the selector picks, faces that merely share a family with a named
+/// face come after those matching it exactly
+fn select<'a>(faces: &[&'a FontFace], selector: Option<&FaceSelector>) -> Option<&'a FontFace> {
+    match selector {
+        None => faces.first().copied(),
+        Some(FaceSelector::Index(index)) => faces.iter().find(|f| f.index == *index).copied(),
+        Some(FaceSelector::Name(name)) => {
+            let candidates: Vec<FontFace> = faces.iter().map(|f| (*f).clone()).collect();
+            let best = matching(name, &candidates).into_iter().next().or_else(|| {
+                let name = normalize(name);
+                candidates.iter().find(|f| normalize(&f.style) == name)
+            })?;
+            faces
+                .iter()
+                .find(|f| (&f.path, f.index) == (&best.path, best.index))
+                .copied()
+        }
+    }
+}
+
+fn load(path: &Path, index: u32) -> Result<LoadedFont> {
+    let name = path.display().to_string();
+    let data = std::fs::read(path).map_err(|_| Error::FontNotFound(name.clone()))?;
+
+    if BitmapFont::is_bitmap(&data) {
+        let font = BitmapFont::parse(&data)
+            .map_err(|e| Error::InvalidFont(name, e.to_string()))?
+            .or_named(&file_stem(path));
+        return Ok(LoadedFont {
+            font: GlyphSource::Bitmap(font),
+            data,
+            index,
+        });
+    }
+
+    let settings = fontdue::FontSettings {
+        collection_index: index,
+        ..fontdue::FontSettings::default()
+    };
+    let font = Font::from_bytes(data.as_slice(), settings)
+        .map_err(|e| Error::InvalidFont(name, e.to_string()))?;
+
+    Ok(LoadedFont {
+        font: GlyphSource::Outline(font),
+        data,
+        index,
+    })
+}
+
+/// Loads a font from a path, or else the best match for a family or alias among the
+/// system's fonts, picking a face of collections with `selector`
+///
+/// # Errors
+///
+/// [`Error::FontNotFound`] when nothing matches, [`Error::InvalidFont`] when the font can't
+/// be loaded
+pub fn search_for_font(query: &str, selector: Option<&FaceSelector>) -> Result<LoadedFont> {
+    let path = Path::new(query);
+    let not_found = || match selector {
+        Some(FaceSelector::Index(index)) => Error::FontNotFound(format!("{query} face {index}")),
+        Some(FaceSelector::Name(name)) => Error::FontNotFound(format!("{query} face {name}")),
+        None => Error::FontNotFound(query.to_string()),
+    };
+
+    if path.is_file() {
+        let data = std::fs::read(path).map_err(|_| Error::FontNotFound(query.to_string()))?;
+        let faces = FontFace::all_in(path, &data);
+
+        // the file may still be a font fontdue understands even if its names can't be read
+        if faces.is_empty() && selector.is_none() {
+            return load(path, 0);
+        }
+
+        let face = select(&faces.iter().collect::<Vec<_>>(), selector).ok_or_else(not_found)?;
+        return load(path, face.index);
+    }
+
+    let face = select(&matching(query, system_fonts()), selector).ok_or_else(not_found)?;
+
+    tracing::debug!(
+        path = %face.path.display(),
+        index = face.index,
+        "{query} resolved to {}",
+        face.full_name
+    );
+    load(&face.path, face.index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(family: &str, style: &str, monospaced: bool) -> FontFace {
+        FontFace {
+            path: PathBuf::from(format!("/fonts/{family}-{style}.ttf")),
+            index: 0,
+            family: family.to_string(),
+            style: style.to_string(),
+            full_name: format!("{family} {style}"),
+            monospaced,
+        }
+    }
+
+    #[test]
+    fn fontconfig_dir_entries() {
+        let config = r#"<fontconfig>
+            <dir>/usr/share/fonts</dir>
+            <dir prefix="xdg">fonts</dir>
+            <dir>~/.fonts</dir>
+        </fontconfig>"#;
+
+        let expected = vec![
+            PathBuf::from("/usr/share/fonts"),
+            PathBuf::from("/home/u/.local/share/fonts"),
+            PathBuf::from("/home/u/.fonts"),
+        ];
+        let result = fontconfig_dirs(
+            config,
+            Some(Path::new("/home/u")),
+            Path::new("/home/u/.local/share"),
+        );
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn symlink_cycles_end() {
+        let dir = std::env::temp_dir().join(format!("derm-fonts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.ttf"), []).unwrap();
+        let _ = std::fs::remove_file(dir.join("loop"));
+        std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+
+        let expected = vec![dir.join("a.ttf")];
+        let mut result = Vec::new();
+        font_files(&dir, &mut result, &mut HashSet::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn family_and_style_names() {
+        let faces = [
+            face("DejaVu Sans Mono", "Book", true),
+            face("DejaVu Sans Mono", "Bold", true),
+            face("DejaVu Sans", "Book", false),
+        ];
+
+        let expected = vec![&faces[1]];
+        let result = matching("dejavu sans-mono bold", &faces);
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+
+        let expected = vec![&faces[0], &faces[1]];
+        let result = matching("DejaVu Sans Mono", &faces);
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn collection_faces_by_index_or_name() {
+        let mut faces = [
+            face("Noto Sans CJK JP", "Regular", false),
+            face("Noto Sans CJK KR", "Regular", false),
+            face("Noto Sans CJK KR", "Bold", false),
+        ];
+        for (index, face) in faces.iter_mut().enumerate() {
+            face.index = u32::try_from(index).unwrap();
+        }
+        let faces: Vec<_> = faces.iter().collect();
+
+        let expected = vec![Some(1), Some(2), Some(1), None];
+        let result: Vec<_> = [
+            FaceSelector::Index(1),
+            FaceSelector::Name(String::from("Noto Sans CJK KR Bold")),
+            FaceSelector::Name(String::from("noto sans cjk kr")),
+            FaceSelector::Index(3),
+        ]
+        .iter()
+        .map(|selector| select(&faces, Some(selector)).map(|f| f.index))
+        .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn monospace_alias_prefers_known_families() {
+        let faces = [
+            face("Anonymous Pro", "Regular", true),
+            face("Liberation Mono", "Bold", true),
+            face("Liberation Mono", "Regular", true),
+            face("Liberation Sans", "Regular", false),
+        ];
+
+        let expected = vec![&faces[2], &faces[1], &faces[0]];
+        let result = matching("monospace", &faces);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
 }
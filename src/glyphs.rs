@@ -0,0 +1,387 @@
+use crate::bitmap_font::BitmapFont;
+use crate::similarity::Points;
+use crate::traits::Pointify;
+
+use fontdue::Font;
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthChar;
+
+/// How outline glyphs are scaled to the cell
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScalePolicy {
+    /// The em box is as tall as the cell, glyphs sit in the cell's top left corner
+    #[default]
+    Em,
+
+    /// The font's line height, gap included, fills the cell with glyphs on its baseline
+    CellHeight,
+
+    /// Ascender to descender fills the cell with glyphs on its baseline
+    AscenderDescender,
+}
+
+/// How outline glyphs are turned into pixels
+#[derive(Clone, Copy, Debug, serde::Serialize)]
+pub struct Rasterization {
+    pub scale: ScalePolicy,
+
+    /// Coverage above which a glyph's pixel is ink
+    pub coverage_threshold: u8,
+
+    /// Pixels every glyph is thickened by in each direction
+    pub embolden: u32,
+
+    /// Glyphs are rasterized this many times larger and averaged back down
+    pub supersample: u32,
+}
+
+impl Default for Rasterization {
+    fn default() -> Self {
+        Rasterization {
+            scale: ScalePolicy::default(),
+            coverage_threshold: 100,
+            embolden: 0,
+            supersample: 1,
+        }
+    }
+}
+
+/// Size an outline glyph is rasterized at and where its bitmap's top left corner lands in
+/// the cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub px: f32,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Placement {
+    #[must_use]
+    pub fn new(font: &Font, c: char, height: u32, scale: ScalePolicy) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let height = height as f32;
+        let em = Placement {
+            px: height,
+            x: 0,
+            y: 0,
+        };
+
+        let Some(unit) = font.horizontal_line_metrics(1.0) else {
+            return em;
+        };
+        let (px, baseline) = match scale {
+            ScalePolicy::Em => return em,
+            ScalePolicy::CellHeight => {
+                let px = height / unit.new_line_size;
+                (px, px * (unit.line_gap / 2.0 + unit.ascent))
+            }
+            ScalePolicy::AscenderDescender => {
+                let px = height / (unit.ascent - unit.descent);
+                (px, px * unit.ascent)
+            }
+        };
+
+        if !px.is_finite() || px <= 0.0 {
+            return em;
+        }
+
+        let metrics = font.metrics(c, px);
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_possible_wrap,
+            clippy::cast_precision_loss
+        )]
+        let y = baseline.round() as i32 - metrics.ymin - metrics.height as i32;
+
+        Placement {
+            px,
+            x: metrics.xmin,
+            y,
+        }
+    }
+}
+
+/// Averages `factor` by `factor` blocks of a glyph rasterized `factor` times larger into a
+/// `width` by `height` cell, its top left corner placed at `(x, y)` cell pixels
+fn downsample(
+    bitmap: &[u8],
+    bitmap_width: usize,
+    factor: usize,
+    (x, y): (i32, i32),
+    (width, height): (usize, usize),
+) -> Vec<u8> {
+    let factor = factor.max(1);
+    let mut sums = vec![0u32; width * height];
+    let offset = |origin: i32, i: usize| {
+        let origin = i64::from(origin) * i64::try_from(factor).unwrap_or(1);
+        usize::try_from(origin + i64::try_from(i).ok()?)
+            .ok()
+            .map(|p| p / factor)
+    };
+
+    for (i, coverage) in bitmap.iter().enumerate() {
+        let (Some(cx), Some(cy)) = (
+            offset(x, i % bitmap_width.max(1)),
+            offset(y, i / bitmap_width.max(1)),
+        ) else {
+            continue;
+        };
+        if cx < width && cy < height {
+            sums[cy * width + cx] += u32::from(*coverage);
+        }
+    }
+
+    let samples = u32::try_from(factor * factor).unwrap_or(u32::MAX);
+    sums.into_iter()
+        .map(|sum| u8::try_from(sum / samples).unwrap_or(u8::MAX))
+        .collect()
+}
+
+/// Thickens coverage by taking the highest within `radius` pixels of each one
+fn embolden(coverage: &[u8], width: usize, radius: usize) -> Vec<u8> {
+    if radius == 0 || width == 0 {
+        return coverage.to_vec();
+    }
+    let height = coverage.len() / width;
+
+    (0..coverage.len())
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            (y.saturating_sub(radius)..(y + radius + 1).min(height))
+                .flat_map(|y| {
+                    (x.saturating_sub(radius)..(x + radius + 1).min(width))
+                        .map(move |x| coverage[y * width + x])
+                })
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// A font of the fallback chain, either outlines rasterized for the cell size or a bitmap
+/// font whose pixels are used as they are
+pub enum GlyphSource {
+    Outline(Font),
+    Bitmap(BitmapFont),
+}
+
+impl GlyphSource {
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            GlyphSource::Outline(font) => font.name(),
+            GlyphSource::Bitmap(font) => font.name(),
+        }
+    }
+
+    #[must_use]
+    pub fn has_glyph(&self, c: char) -> bool {
+        match self {
+            GlyphSource::Outline(font) => font.has_glyph(c),
+            GlyphSource::Bitmap(font) => font.has_glyph(c),
+        }
+    }
+
+    /// Size of the cells a bitmap font was drawn for
+    #[must_use]
+    pub fn cell_size(&self) -> Option<(u32, u32)> {
+        match self {
+            GlyphSource::Outline(_) => None,
+            GlyphSource::Bitmap(font) => Some(font.cell_size()),
+        }
+    }
+
+    /// Columns taken by the chars used as candidates. Outline glyphs are matched against
+    /// square cells, which fit chars two columns wide, while a bitmap font's cell is a
+    /// single column.
+    fn candidate_width(&self) -> usize {
+        match self {
+            GlyphSource::Outline(_) => 2,
+            GlyphSource::Bitmap(_) => 1,
+        }
+    }
+
+    fn chars(&self) -> Vec<char> {
+        match self {
+            GlyphSource::Outline(font) => font.chars().keys().copied().collect(),
+            GlyphSource::Bitmap(font) => font.chars().collect(),
+        }
+    }
+
+    /// Ink of a glyph in a cell, bitmap glyphs are used as they are
+    #[must_use]
+    pub fn points(&self, c: char, cell: (u32, u32), raster: &Rasterization) -> Option<Points> {
+        if let GlyphSource::Bitmap(font) = self {
+            return font.glyph(c).cloned();
+        }
+
+        let points = self
+            .coverage(c, cell, raster)
+            .to_points(cell.0 as usize)
+            .ok()?
+            .filter(|(_, _, p)| *p > u16::from(raster.coverage_threshold))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+
+        Some(points)
+    }
+
+    /// Coverage of every pixel of a glyph's cell, row major
+    #[must_use]
+    pub fn coverage(&self, c: char, cell: (u32, u32), raster: &Rasterization) -> Vec<u8> {
+        let (width, height) = (cell.0 as usize, cell.1 as usize);
+
+        match self {
+            GlyphSource::Outline(font) => {
+                let placement = Placement::new(font, c, cell.1, raster.scale);
+                let factor = raster.supersample.max(1);
+
+                #[allow(clippy::cast_precision_loss)]
+                let (metrics, bitmap) = font.rasterize(c, placement.px * factor as f32);
+                let coverage = downsample(
+                    &bitmap,
+                    metrics.width,
+                    factor as usize,
+                    (placement.x, placement.y),
+                    (width, height),
+                );
+
+                embolden(&coverage, width, raster.embolden as usize)
+            }
+            GlyphSource::Bitmap(font) => {
+                let mut coverage = vec![0; width * height];
+                for &(x, y) in font.glyph(c).into_iter().flatten() {
+                    if let Some(pixel) = coverage.get_mut(usize::from(y) * width + usize::from(x)) {
+                        *pixel = u8::MAX;
+                    }
+                }
+                coverage
+            }
+        }
+    }
+}
+
+/// Candidate glyphs of a font rasterized once for a given cell size, shared by every cell and
+/// frame rendered with that size
+pub struct GlyphSet {
+    glyphs: Vec<(char, Points)>,
+}
+
+impl GlyphSet {
+    /// Candidates from every font, each char taken from the first font covering it. Chars
+    /// are candidates when they are in `charset`, or without one when they are as wide as
+    /// the first font's cells.
+    pub fn new(
+        fonts: &[GlyphSource],
+        cell_size: (u32, u32),
+        raster: &Rasterization,
+        charset: Option<&str>,
+    ) -> Self {
+        let width = fonts.first().map_or(2, GlyphSource::candidate_width);
+
+        let mut sources = BTreeMap::new();
+        for font in fonts {
+            for c in font.chars() {
+                let candidate = match charset {
+                    Some(charset) => charset.contains(c),
+                    None => c.width().is_some_and(|w| w == width),
+                };
+                if candidate {
+                    sources.entry(c).or_insert(font);
+                }
+            }
+        }
+
+        let glyphs = sources
+            .into_iter()
+            .filter_map(|(c, font)| Some((c, font.points(c, cell_size, raster)?)))
+            .collect();
+
+        GlyphSet { glyphs }
+    }
+
+    /// Rasterized points of a glyph
+    #[must_use]
+    pub fn get(&self, c: char) -> Option<&Points> {
+        self.glyphs
+            .binary_search_by_key(&c, |(glyph, _)| *glyph)
+            .ok()
+            .map(|i| &self.glyphs[i].1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(char, Points)> {
+        self.glyphs.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supersampled_coverage_is_averaged_into_place() {
+        // 4x2 bitmap at twice the size, placed one pixel right in a 3x1 cell
+        let bitmap = [255, 255, 0, 255, 255, 255, 0, 0];
+
+        let expected = vec![0, 255, 63];
+        let result = downsample(&bitmap, 4, 2, (1, 0), (3, 1));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn embolden_spreads_coverage() {
+        let coverage = [0, 0, 0, 0, 0, 200, 0, 0, 0, 0, 0, 0];
+
+        let expected = vec![200, 200, 200, 0, 200, 200, 200, 0, 200, 200, 200, 0];
+        let result = embolden(&coverage, 4, 1);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn first_font_covering_a_char_supplies_it() {
+        // `b` is a left column in the first font and a bottom row in the second
+        let font = |glyphs: &str| {
+            let bdf = format!("STARTFONT 2.1\nFONTBOUNDINGBOX 2 2 0 0\n{glyphs}ENDFONT\n");
+            GlyphSource::Bitmap(BitmapFont::parse(bdf.as_bytes()).unwrap())
+        };
+        let fonts = [
+            font(
+                "STARTCHAR a\nENCODING 97\nBBX 2 2 0 0\nBITMAP\nC0\nC0\nENDCHAR\n\
+                STARTCHAR b\nENCODING 98\nBBX 1 2 0 0\nBITMAP\n80\n80\nENDCHAR\n",
+            ),
+            font(
+                "STARTCHAR b\nENCODING 98\nBBX 2 1 0 0\nBITMAP\nC0\nENDCHAR\n\
+                STARTCHAR c\nENCODING 99\nBBX 1 2 1 0\nBITMAP\n80\n80\nENDCHAR\n",
+            ),
+        ];
+        let glyphs = GlyphSet::new(&fonts, (2, 2), &Rasterization::default(), Some("abc"));
+
+        let expected = vec![
+            ('a', vec![(0, 0), (0, 1), (1, 0), (1, 1)]),
+            ('b', vec![(0, 0), (0, 1)]),
+            ('c', vec![(1, 0), (1, 1)]),
+        ];
+        let result: Vec<_> = glyphs
+            .iter()
+            .map(|(c, points)| {
+                let mut points: Vec<_> = points.iter().copied().collect();
+                points.sort_unstable();
+                (*c, points)
+            })
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
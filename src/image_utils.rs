@@ -1,17 +1,134 @@
-use image::{DynamicImage, GenericImageView, SubImage};
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb, RgbImage, Rgba, SubImage};
 use std::vec::Vec;
 
-// row major traversal
-pub fn img_partitions_from(
-    img: &DynamicImage,
+type Result<T> = std::result::Result<T, Error>;
+
+/// Luma below which a pixel is considered ink
+const INK_THRESHOLD: u8 = 245;
+
+/// Alpha below which a pixel is considered transparent
+const ALPHA_THRESHOLD: u8 = 128;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    InvalidColor(String),
+    InvalidDimensions(String),
+    BufferSize { width: u32, height: u32, len: usize },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidColor(s) => write!(f, "Invalid color '{s}', expected #rrggbb or #rgb"),
+            Error::InvalidDimensions(s) => write!(f, "Invalid dimensions '{s}', expected WxH"),
+            Error::BufferSize { width, height, len } => write!(
+                f,
+                "A {width}x{height} image needs {} bytes, got {len}",
+                u64::from(*width) * u64::from(*height)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AlphaMode {
+    /// Transparent pixels are never ink
+    #[default]
+    Background,
+
+    /// Composite the image onto the background color before rendering
+    Composite,
+}
+
+/// Parses `#rrggbb` or `#rgb`, the leading `#` is optional
+///
+/// # Errors
+///
+/// [`Error::InvalidColor`] for anything else
+pub fn parse_color(s: &str) -> Result<Rgb<u8>> {
+    let hex = s.trim().trim_start_matches('#');
+    let invalid = || Error::InvalidColor(s.to_string());
+
+    let expanded = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return Err(invalid()),
+    };
+
+    let channel = |i: usize| {
+        expanded
+            .get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or_else(invalid)
+    };
+
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// Blends every pixel onto an opaque background using its alpha
+#[must_use]
+pub fn composite(img: &DynamicImage, background: Rgb<u8>) -> DynamicImage {
+    let rgba = img.to_rgba8();
+
+    DynamicImage::ImageRgb8(RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *rgba.get_pixel(x, y);
+        let blend = |fg: u8, bg: u8| {
+            let (fg, bg, a) = (u16::from(fg), u16::from(bg), u16::from(a));
+            u8::try_from((fg * a + bg * (255 - a) + 127) / 255).unwrap_or(u8::MAX)
+        };
+
+        Rgb([
+            blend(r, background[0]),
+            blend(g, background[1]),
+            blend(b, background[2]),
+        ])
+    }))
+}
+
+/// Whether a pixel of a grayscale image is dark and opaque enough to be drawn
+#[must_use]
+pub fn is_ink(p: Rgba<u8>) -> bool {
+    p[0] < INK_THRESHOLD && p[3] >= ALPHA_THRESHOLD
+}
+
+/// Parses `WxH` dimensions
+///
+/// # Errors
+///
+/// [`Error::InvalidDimensions`] for anything else, or when either dimension is 0
+pub fn parse_dimensions(s: &str) -> Result<(u32, u32)> {
+    s.split_once('x')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| Error::InvalidDimensions(s.to_string()))
+}
+
+/// 8 bit grayscale image from raw row major pixels
+///
+/// # Errors
+///
+/// [`Error::BufferSize`] unless there's exactly one byte per pixel
+pub fn luma_image(width: u32, height: u32, pixels: Vec<u8>) -> Result<DynamicImage> {
+    let len = pixels.len();
+    GrayImage::from_raw(width, height, pixels)
+        .filter(|_| u64::try_from(len).is_ok_and(|len| len == u64::from(width) * u64::from(height)))
+        .map(DynamicImage::ImageLuma8)
+        .ok_or(Error::BufferSize { width, height, len })
+}
+
+// row major traversal, as (x, y, width, height)
+#[must_use]
+pub fn partition_rects(
+    (img_width, img_height): (u32, u32),
     partition_width: u32,
     partition_height: u32,
     keep_partial_partitions: bool,
-) -> Vec<SubImage<&DynamicImage>> {
-    let (img_width, img_height) = img.dimensions();
-
+) -> Vec<(u32, u32, u32, u32)> {
     if partition_width >= img_width || partition_height >= img_height {
-        return vec![img.view(0, 0, img_width, img_height)];
+        return vec![(0, 0, img_width, img_height)];
     }
 
     let partition_start_coordinates = (0..img_height)
@@ -30,7 +147,7 @@ pub fn img_partitions_from(
         .into_iter()
         .filter_map(|(x, y)| {
             if x + partition_width <= img_width && y + partition_height <= img_height {
-                return Some(img.view(x, y, partition_width, partition_height));
+                return Some((x, y, partition_width, partition_height));
             }
 
             if keep_partial_partitions {
@@ -45,10 +162,164 @@ pub fn img_partitions_from(
                     partition_height
                 };
 
-                return Some(img.view(x, y, truncated_width, truncated_height));
+                return Some((x, y, truncated_width, truncated_height));
             }
 
             None
         })
         .collect()
 }
+
+// row major traversal
+#[must_use]
+pub fn img_partitions_from(
+    img: &DynamicImage,
+    partition_width: u32,
+    partition_height: u32,
+    keep_partial_partitions: bool,
+) -> Vec<SubImage<&DynamicImage>> {
+    partition_rects(
+        img.dimensions(),
+        partition_width,
+        partition_height,
+        keep_partial_partitions,
+    )
+    .into_iter()
+    .map(|(x, y, width, height)| img.view(x, y, width, height))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn parse_long_color() {
+        let expected = Ok(Rgb([0x12, 0xab, 0xff]));
+        let result = parse_color("#12abff");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_short_color() {
+        let expected = Ok(Rgb([0xff, 0x00, 0xcc]));
+        let result = parse_color("f0c");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_invalid_color() {
+        let expected = Err(Error::InvalidColor(String::from("#12345g")));
+        let result = parse_color("#12345g");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_frame_dimensions() {
+        let expected = Ok((320, 240));
+        let result = parse_dimensions("320x240");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_zero_dimensions() {
+        let expected = Err(Error::InvalidDimensions(String::from("0x240")));
+        let result = parse_dimensions("0x240");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn luma_buffer_matches_dimensions() {
+        let expected = vec![
+            Ok(vec![0, 128, 255, 64]),
+            Err(Error::BufferSize {
+                width: 2,
+                height: 2,
+                len: 5,
+            }),
+        ];
+        let result: Vec<_> = [vec![0, 128, 255, 64], vec![0; 5]]
+            .into_iter()
+            .map(|pixels| luma_image(2, 2, pixels).map(|img| img.into_luma8().into_raw()))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn partition_full_rects() {
+        let expected = vec![(0, 0, 2, 2), (2, 0, 2, 2)];
+        let result = partition_rects((5, 3), 2, 2, false);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn partition_partial_rects() {
+        let expected = vec![
+            (0, 0, 2, 2),
+            (2, 0, 2, 2),
+            (4, 0, 1, 2),
+            (0, 2, 2, 1),
+            (2, 2, 2, 1),
+            (4, 2, 1, 1),
+        ];
+        let result = partition_rects((5, 3), 2, 2, true);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn composite_transparent_pixel() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 0])));
+
+        let expected = Rgba([10, 20, 30, 255]);
+        let result = composite(&img, Rgb([10, 20, 30])).get_pixel(0, 0);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn transparent_pixel_is_not_ink() {
+        let expected = false;
+        let result = is_ink(Rgba([0, 0, 0, 0]));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::iter::repeat_n;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Border {
+    /// Rows only
+    None,
+
+    /// `|` on either side of every row, unpadded
+    #[default]
+    Sides,
+
+    /// `+`, `-` and `|`
+    Ascii,
+
+    /// Heavy box drawing characters
+    Unicode,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+}
+
+/// Corners and edges of a box, clockwise from the top left corner
+pub struct BoxChars {
+    top_left: char,
+    horizontal: char,
+    top_right: char,
+    vertical: char,
+    bottom_right: char,
+    bottom_left: char,
+}
+
+pub const ASCII_BOX: BoxChars = BoxChars {
+    top_left: '+',
+    horizontal: '-',
+    top_right: '+',
+    vertical: '|',
+    bottom_right: '+',
+    bottom_left: '+',
+};
+
+pub const UNICODE_BOX: BoxChars = BoxChars {
+    top_left: '┏',
+    horizontal: '━',
+    top_right: '┓',
+    vertical: '┃',
+    bottom_right: '┛',
+    bottom_left: '┗',
+};
+
+fn pad(out: &mut String, width: usize) {
+    out.extend(repeat_n(' ', width));
+}
+
+/// Draws a box around rows, padding each to the widest row plus `padding` spaces per side
+pub fn boxed<S: AsRef<str>>(rows: &[S], chars: &BoxChars, padding: usize) -> Vec<String> {
+    let width = rows.iter().map(|r| r.as_ref().width()).max().unwrap_or(0);
+    let horizontal = |left: char, right: char| {
+        let mut line = String::from(left);
+        line.extend(repeat_n(chars.horizontal, width + 2 * padding));
+        line.push(right);
+        line
+    };
+
+    let framed = rows.iter().map(|row| {
+        let row = row.as_ref();
+        let mut line = String::from(chars.vertical);
+        pad(&mut line, padding);
+        line.push_str(row);
+        pad(&mut line, width - row.width() + padding);
+        line.push(chars.vertical);
+        line
+    });
+
+    std::iter::once(horizontal(chars.top_left, chars.top_right))
+        .chain(framed)
+        .chain(std::iter::once(horizontal(
+            chars.bottom_left,
+            chars.bottom_right,
+        )))
+        .collect()
+}
+
+/// How rows of characters are framed when written out
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    pub border: Border,
+    pub alignment: Alignment,
+    pub title: Option<String>,
+    pub caption: Option<String>,
+
+    /// Width to center within, the widest line when unset
+    pub width: Option<usize>,
+}
+
+impl Layout {
+    /// Framed, titled and aligned lines
+    pub fn lines<S: AsRef<str>>(&self, rows: &[S]) -> Vec<String> {
+        let mut text = String::new();
+        self.write(rows, &mut text);
+        text.lines().map(String::from).collect()
+    }
+
+    /// Writes every line, each followed by a newline, without allocating beyond `out`
+    pub fn write<S: AsRef<str>>(&self, rows: &[S], out: &mut String) {
+        let widest_row = rows.iter().map(|r| r.as_ref().width()).max().unwrap_or(0);
+        let chars = match self.border {
+            Border::None | Border::Sides => None,
+            Border::Ascii => Some(&ASCII_BOX),
+            Border::Unicode => Some(&UNICODE_BOX),
+        };
+        let framed_width = |row_width: usize| match self.border {
+            Border::None => row_width,
+            Border::Sides => row_width + 4,
+            Border::Ascii | Border::Unicode => widest_row + 4,
+        };
+
+        let widest = self
+            .title
+            .iter()
+            .chain(&self.caption)
+            .map(|line| line.width())
+            .chain((!rows.is_empty() || chars.is_some()).then(|| framed_width(widest_row)))
+            .max()
+            .unwrap_or(0);
+        let width = self.width.unwrap_or(widest).max(widest);
+        let indent = |out: &mut String, line_width: usize| {
+            if self.alignment == Alignment::Center {
+                pad(out, (width - line_width) / 2);
+            }
+        };
+        let horizontal = |out: &mut String, left: char, fill: char, right: char| {
+            indent(out, framed_width(widest_row));
+            out.push(left);
+            out.extend(repeat_n(fill, widest_row + 2));
+            out.push(right);
+            out.push('\n');
+        };
+
+        if let Some(title) = &self.title {
+            indent(out, title.width());
+            out.push_str(title);
+            out.push('\n');
+        }
+
+        if let Some(chars) = chars {
+            horizontal(out, chars.top_left, chars.horizontal, chars.top_right);
+        }
+        for row in rows {
+            let row = row.as_ref();
+            let row_width = row.width();
+            indent(out, framed_width(row_width));
+            match (self.border, chars) {
+                (Border::Sides, _) => {
+                    out.push_str("| ");
+                    out.push_str(row);
+                    out.push_str(" |");
+                }
+                (_, Some(chars)) => {
+                    out.push(chars.vertical);
+                    out.push(' ');
+                    out.push_str(row);
+                    pad(out, widest_row - row_width + 1);
+                    out.push(chars.vertical);
+                }
+                (_, None) => out.push_str(row),
+            }
+            out.push('\n');
+        }
+        if let Some(chars) = chars {
+            horizontal(out, chars.bottom_left, chars.horizontal, chars.bottom_right);
+        }
+
+        if let Some(caption) = &self.caption {
+            indent(out, caption.width());
+            out.push_str(caption);
+            out.push('\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_border() {
+        let layout = Layout {
+            border: Border::None,
+            ..Layout::default()
+        };
+
+        let expected = vec!["ab", "c"];
+        let result = layout.lines(&["ab", "c"]);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn default_border_frames_sides() {
+        let layout = Layout::default();
+
+        let expected = vec!["| ab |", "| c |"];
+        let result = layout.lines(&["ab", "c"]);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn ascii_border_pads_rows() {
+        let layout = Layout {
+            border: Border::Ascii,
+            ..Layout::default()
+        };
+
+        let expected = vec!["+----+", "| ab |", "| c  |", "+----+"];
+        let result = layout.lines(&["ab", "c"]);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn unicode_border_wide_chars() {
+        let layout = Layout {
+            border: Border::Unicode,
+            ..Layout::default()
+        };
+
+        let expected = vec!["┏━━━━┓", "┃ ♊ ┃", "┃ a  ┃", "┗━━━━┛"];
+        let result = layout.lines(&["♊", "a"]);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn centered_title_and_caption() {
+        let layout = Layout {
+            border: Border::None,
+            alignment: Alignment::Center,
+            title: Some(String::from("title")),
+            caption: Some(String::from("c")),
+            width: None,
+        };
+
+        let expected = vec!["title", " abc", "  c"];
+        let result = layout.lines(&["abc"]);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn centered_within_width() {
+        let layout = Layout {
+            border: Border::None,
+            alignment: Alignment::Center,
+            width: Some(7),
+            ..Layout::default()
+        };
+
+        let expected = vec!["  abc"];
+        let result = layout.lines(&["abc"]);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn write_appends_centered_lines() {
+        let layout = Layout {
+            border: Border::Sides,
+            alignment: Alignment::Center,
+            title: Some(String::from("t")),
+            ..Layout::default()
+        };
+        let mut result = String::from("\x1b[H");
+        layout.write(&["abc", "a"], &mut result);
+
+        let expected = "\x1b[H   t\n| abc |\n | a |\n";
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
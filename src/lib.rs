@@ -0,0 +1,10 @@
+pub mod bitmap_font;
+pub mod edges;
+pub mod error;
+pub mod font_utils;
+pub mod glyphs;
+pub mod image_utils;
+pub mod preprocess;
+pub mod render;
+pub mod similarity;
+mod traits;
@@ -0,0 +1,52 @@
+use std::io::IsTerminal;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding filter directives, e.g. `DERM_LOG=debug` or
+/// `DERM_LOG=derm_rs::render=trace`, taking precedence over the command line verbosity
+pub const LOG_ENV: &str = "DERM_LOG";
+
+/// Level enabled by `-q`, nothing and each additional `-v`
+fn level(verbosity: u8, quiet: bool) -> &'static str {
+    match (quiet, verbosity) {
+        (true, _) => "error",
+        (false, 0) => "warn",
+        (false, 1) => "info",
+        (false, 2) => "debug",
+        (false, _) => "trace",
+    }
+}
+
+/// Sends log records to stderr so stdout only ever carries the output
+pub fn init(verbosity: u8, quiet: bool) {
+    let filter = EnvFilter::try_from_env(LOG_ENV)
+        .unwrap_or_else(|_| EnvFilter::new(level(verbosity, quiet)));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_overrides_verbosity() {
+        let expected = vec!["warn", "info", "debug", "trace", "trace", "error"];
+        let result = vec![
+            level(0, false),
+            level(1, false),
+            level(2, false),
+            level(3, false),
+            level(7, false),
+            level(2, true),
+        ];
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -1,149 +1,644 @@
-mod font_utils;
-mod image_utils;
-mod similarity;
-mod traits;
+mod animation;
+mod batch;
+mod config;
+mod diagnose;
+mod layout;
+mod logging;
+mod output;
+mod playback;
+mod terminal_font;
+mod timings;
+mod video;
 mod visualize;
 
-use image_utils::img_partitions_from;
-use itertools::Itertools;
-use similarity::Points;
-use traits::Pointify;
+use derm_rs::render::{Grid, Renderer, Settings, SimilarityMetric};
+use derm_rs::{edges, error, font_utils, glyphs, image_utils, preprocess};
+use timings::Timings;
 use visualize::print_to_console;
 
-use clap::Parser;
-use fontdue::Font;
-use image::{DynamicImage, GenericImageView, Pixel, SubImage};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use image::{DynamicImage, GenericImageView};
 use rayon::prelude::*;
-use unicode_width::UnicodeWidthChar;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-#[derive(clap::ValueEnum, Clone, Default, Debug, serde::Serialize)]
-#[serde(rename_all = "kebab-case")]
-enum SimilarityMetric {
-    /// Hausdorff Distance
-    #[default]
-    Hausdorff,
+/// Unicode image renderer
+#[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+#[command(version, about, long_about = None, disable_version_flag=true)]
+struct Args {
+    /// Input image, or `-` to read one from stdin. Animated inputs use their first frame for
+    /// formats other than text. Given several images, directories or glob patterns, every
+    /// image is rendered into `--output-dir`.
+    #[arg(short = 'i', long = "image", value_name = "IMAGE", num_args = 1.., required_unless_present_any = ["frame_size", "list_fonts"])]
+    images: Vec<String>,
+
+    /// Read raw 8 bit grayscale video frames of this size from stdin instead of an image
+    #[arg(long, value_name = "WxH", value_parser = image_utils::parse_dimensions, conflicts_with = "images")]
+    frame_size: Option<(u32, u32)>,
+
+    /// Settings profile of the config files to use on top of their defaults, overridden by
+    /// any flag given
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Fonts to use during rendering, each a path or an installed family or full name such
+    /// as `DejaVu Sans Mono Bold`, or `monospace`, `sans-serif` or `serif`. Given several,
+    /// separated by commas or repeated, each char comes from the first font covering it.
+    /// Defaults to the font configured for the terminal, or else `monospace`.
+    #[arg(short, long, value_delimiter = ',')]
+    font: Vec<String>,
+
+    /// Face to use from the first font when it is a collection (`.ttc` or `.otc`), an index
+    /// from 0 or a name such as `Noto Sans CJK JP`
+    #[arg(long, value_name = "INDEX|NAME")]
+    font_index: Option<font_utils::FaceSelector>,
+
+    /// List installed fonts, only those matching QUERY when given, best match first
+    #[arg(long, value_name = "QUERY", num_args = 0..=1, default_missing_value = "")]
+    list_fonts: Option<String>,
+
+    /// Scale of unicode image, ignored when the first font is a bitmap font, which is
+    /// matched at its own cell size
+    #[arg(short, long, default_value_t = 50)]
+    pixels_per_char: u8,
+
+    /// How outline glyphs are scaled to fit the cell
+    #[arg(long, default_value_t, value_enum)]
+    scale: glyphs::ScalePolicy,
+
+    /// Coverage above which a rasterized glyph's pixel counts as ink
+    #[arg(long, value_name = "0-255", default_value_t = 100)]
+    coverage_threshold: u8,
+
+    /// Pixels to thicken rasterized glyphs by
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    embolden: u8,
+
+    /// Rasterize glyphs this many times larger and average them back down
+    #[arg(long, value_name = "FACTOR", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=8))]
+    supersample: u8,
+
+    /// Characters the image may be drawn with, whatever their width. By default every
+    /// character of the fonts as wide as a cell is.
+    #[arg(long, value_name = "CHARS")]
+    charset: Option<String>,
+
+    /// similarity metric
+    #[arg(short, long, default_value_t, value_enum)]
+    similarity_metric: SimilarityMetric,
+
+    /// Preprocessing operation applied before rendering, may be repeated (e.g. `resize=200x`, `crop=100x50+0+0`, `rotate=90`, `flip=h`, `brightness=10`, `contrast=20`, `gamma=1.2`, `unsharp=1.5,2`, `blur=1.0`, `equalize`)
+    #[arg(long = "preprocess", value_name = "OP")]
+    preprocess: Vec<preprocess::Operation>,
+
+    /// File of preprocessing operations, one per line, applied before any `--preprocess` operations
+    #[arg(long, value_name = "PATH")]
+    preprocess_file: Option<String>,
+
+    /// How transparent pixels are handled
+    #[arg(long, default_value_t, value_enum)]
+    alpha: image_utils::AlphaMode,
+
+    /// Background color transparent images are composited onto with `--alpha composite`
+    #[arg(long, default_value = "#ffffff", value_parser = image_utils::parse_color)]
+    background: image::Rgb<u8>,
+
+    /// Convert the image into a line drawing before matching
+    #[arg(long, value_enum)]
+    edges: Option<edges::EdgeDetector>,
+
+    /// Gradient magnitude, between 0 and 1, above which a pixel is considered an edge
+    #[arg(long, default_value_t = 0.25)]
+    edge_threshold: f32,
+
+    /// Favor the line glyph following the gradient direction in strongly oriented cells, when
+    /// it's a candidate, by halving its match error
+    #[arg(long)]
+    edge_orientation: bool,
+
+    /// How animated images are output
+    #[arg(long, default_value_t, value_enum)]
+    animation: animation::AnimationMode,
+
+    /// Restart animations from the first frame once they finish
+    #[arg(long = "loop")]
+    looping: bool,
+
+    /// Border drawn around the output
+    #[arg(long, default_value_t, value_enum)]
+    border: layout::Border,
+
+    /// Alignment of the output, centering uses the terminal width when writing to one
+    #[arg(long, default_value_t, value_enum)]
+    align: layout::Alignment,
+
+    /// Title printed above the output
+    #[arg(long)]
+    title: Option<String>,
 
-    /// Hamming Distance
-    Hamming,
+    /// Caption printed below the output
+    #[arg(long)]
+    caption: Option<String>,
 
-    /// Levenshtein Distance
-    Levenshtein,
+    /// Output format
+    #[arg(long, default_value_t, value_enum)]
+    format: output::OutputFormat,
+
+    /// File to write to instead of stdout
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Directory every rendering of several images is written to
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    output_dir: Option<String>,
+
+    /// Name of the files written to `--output-dir`, where `{stem}` is the input's file name
+    /// without its extension, `{name}` its whole file name, `{index}` its position among the
+    /// inputs and `{ext}` the output format's extension
+    #[arg(long, value_name = "TEMPLATE", default_value = "{stem}.{ext}")]
+    output_name: String,
+
+    /// Draw glyphs as paths in SVG output, so it renders without the font
+    #[arg(long)]
+    outline_glyphs: bool,
+
+    /// Place the input next to the rendering in PNG output
+    #[arg(long)]
+    side_by_side: bool,
+
+    /// Runner up characters kept for every cell, listed in JSON output and by `--inspect`
+    #[arg(long, default_value_t = 3)]
+    candidates: usize,
+
+    /// Print a heatmap of per-cell match error instead of the rendering
+    #[arg(long)]
+    diagnose: bool,
+
+    /// Worst matched cells highlighted and listed by `--diagnose`
+    #[arg(long, default_value_t = 5, value_name = "N")]
+    worst: usize,
+
+    /// List the closest alternatives for a cell instead of the rendering, may be repeated
+    #[arg(long, value_name = "ROW,COLUMN", value_parser = diagnose::parse_cell)]
+    inspect: Vec<(usize, usize)>,
+
+    /// What happens when a cell can't be matched to any glyph
+    #[arg(long, default_value_t, value_enum)]
+    on_error: error::ErrorPolicy,
+
+    /// Character used for cells that can't be matched
+    #[arg(long, default_value_t = ' ')]
+    fallback_char: char,
+
+    /// Log more to stderr, repeat for more detail (`-v` info, `-vv` debug, `-vvv` trace),
+    /// overridden by the `DERM_LOG` environment variable
+    #[arg(short, long, short_alias = 'V', action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Show a cell, or a rectangle of cells, next to its matched glyph in verbose output
+    #[arg(long, value_name = "ROW,COLUMN[:ROW,COLUMN]", value_parser = diagnose::parse_cell_range, requires = "verbose")]
+    debug_cells: Option<diagnose::CellRange>,
 }
 
-fn match_char<F, T, E>(
-    img: &SubImage<&DynamicImage>,
-    font: &Font,
-    error_calc: F,
-) -> Result<char, Box<dyn std::error::Error + Sync + Send>>
-where
-    F: Fn(&Points, &Points) -> Result<T, E>,
-    T: PartialOrd,
-    E: std::fmt::Display,
-{
-    let img_points = img
-        .pixels()
-        .filter(|(_, _, p)| p.channels()[0] < 245)
-        .map(
-            |(x, y, _)| -> Result<(u16, u16), Box<dyn std::error::Error + Send + Sync>> {
-                Ok((u16::try_from(x)?, u16::try_from(y)?))
+impl Args {
+    /// Parses the command line over the settings of the user and project config files, their
+    /// defaults overridden by `--profile`
+    fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let cli: Vec<_> = std::env::args_os().collect();
+        let matches = Args::command().get_matches_from(&cli);
+
+        let profile = matches.get_one::<String>("profile").map(String::as_str);
+        let settings = config::Config::load()?.settings(profile)?;
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let config_args = config::args(&settings, &Args::command(), given)?;
+
+        let args = cli
+            .iter()
+            .take(1)
+            .cloned()
+            .chain(config_args.into_iter().map(Into::into))
+            .chain(cli.iter().skip(1).cloned());
+        Ok(Args::from_arg_matches(
+            &Args::command().get_matches_from(args),
+        )?)
+    }
+
+    fn settings(&self) -> error::Result<Settings> {
+        let mut operations = match &self.preprocess_file {
+            Some(path) => preprocess::operations_from_file(path)?,
+            None => Vec::new(),
+        };
+        operations.extend(self.preprocess.iter().cloned());
+
+        Ok(Settings {
+            pixels_per_char: self.pixels_per_char.into(),
+            rasterization: glyphs::Rasterization {
+                scale: self.scale,
+                coverage_threshold: self.coverage_threshold,
+                embolden: self.embolden.into(),
+                supersample: self.supersample.into(),
             },
-        )
-        .collect::<Result<Points, _>>()?;
+            charset: self.charset.clone(),
+            similarity_metric: self.similarity_metric,
+            alpha: self.alpha,
+            background: self.background,
+            operations,
+            edges: self.edges,
+            edge_threshold: self.edge_threshold,
+            edge_orientation: self.edge_orientation,
+            candidates: self.candidates,
+            on_error: self.on_error,
+            fallback_char: self.fallback_char,
+        })
+    }
 
-    Ok(font
-        .chars()
-        .iter()
-        .filter(|(c, _)| c.width().is_some_and(|w| w == 2))
-        .map(|(c, _)| -> Result<_, Box<dyn std::error::Error>> {
-            #[allow(clippy::cast_precision_loss)]
-            let (metrics, bitmap) = font.rasterize(*c, img.width() as f32);
-
-            let font_points: Points = bitmap
-                .to_points(metrics.width)?
-                .filter(|(_, _, p)| *p > 100)
-                .map(|(x, y, _)| (x, y))
+    fn layout(&self, to_terminal: bool) -> layout::Layout {
+        layout::Layout {
+            border: self.border,
+            alignment: self.align,
+            title: self.title.clone(),
+            caption: self.caption.clone(),
+            width: to_terminal
+                .then(crossterm::terminal::size)
+                .and_then(Result::ok)
+                .map(|(columns, _)| columns.into()),
+        }
+    }
+}
+
+fn output_writer(
+    path: Option<&str>,
+) -> Result<Box<dyn std::io::Write>, Box<dyn std::error::Error>> {
+    Ok(match path {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).map_err(|_| format!("unable to create {path}"))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    })
+}
+
+fn write_frames(
+    args: &Args,
+    output: Option<&str>,
+    layout: &layout::Layout,
+    renderer: &Renderer,
+    font_files: &[(Vec<u8>, u32)],
+    frames: &[(Grid, Duration, DynamicImage)],
+    to_terminal: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match (frames, args.animation) {
+        ([(grid, _, source)], _) => {
+            let fonts: Vec<_> = renderer
+                .fonts()
+                .iter()
+                .zip(font_files)
+                .map(|(font, (data, index))| output::FontFile {
+                    font,
+                    data,
+                    index: *index,
+                })
                 .collect();
 
+            let document = output::Document {
+                grid,
+                layout,
+                fonts: &fonts,
+                cell_size: renderer.cell_size(),
+                background: args.background,
+                settings: renderer.settings(),
+                outline_glyphs: args.outline_glyphs,
+                source: args.side_by_side.then_some(source),
+            };
+
+            let mut out = output_writer(output)?;
+            output::write(args.format, &document, &mut out)?;
+            out.flush()?;
+        }
+        (frames, animation::AnimationMode::Play) if to_terminal => {
+            let frames: Vec<_> = frames
+                .iter()
+                .map(|(grid, delay, _)| (layout.lines(&grid.text_rows()), *delay))
+                .collect();
+
+            playback::play(&frames, args.looping)?;
+        }
+        (frames, _) => {
+            let mut out = output_writer(output)?;
+            for (i, (grid, _, _)) in frames.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                let mut text = String::new();
+                layout.write(&grid.text_rows(), &mut text);
+                out.write_all(text.as_bytes())?;
+            }
+            out.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn list_fonts(query: &str) -> std::io::Result<()> {
+    let faces = font_utils::system_fonts();
+    let matches = if query.is_empty() {
+        faces.iter().collect()
+    } else {
+        font_utils::matching(query, faces)
+    };
+
+    let mut out = std::io::stdout().lock();
+    for face in matches {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}",
+            face.family,
+            face.style,
+            face.path.display(),
+            face.index
+        )?;
+    }
+    out.flush()
+}
+
+/// Prepares and matches every frame, recording the time spent in each stage across frames
+fn render_frames(
+    args: &Args,
+    renderer: &Renderer,
+    frames: Vec<animation::Frame>,
+    timings: &mut Timings,
+) -> error::Result<Vec<(Grid, Duration, DynamicImage)>> {
+    let frames: Vec<_> = frames
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let _frame = tracing::debug_span!("frame", index = i).entered();
+
+            let start = Instant::now();
+            let prepared = tracing::info_span!("stage", stage = "prepare")
+                .in_scope(|| renderer.prepare(frame.image));
+            let preparing = start.elapsed();
+
+            if tracing::enabled!(tracing::Level::DEBUG) {
+                let img = &prepared.gray;
+                print_to_console(&img.pixels(), img.width() as usize, |(_, _, p)| {
+                    image_utils::is_ink(p)
+                });
+            }
+
+            let start = Instant::now();
+            let grid = tracing::info_span!("stage", stage = "match")
+                .in_scope(|| renderer.render_prepared(&prepared))?;
+            let matching = start.elapsed();
+
+            let comparison = match args.debug_cells {
+                Some(range) if i == 0 => diagnose::compare_cells(renderer, &prepared, &grid, range),
+                _ => Vec::new(),
+            };
+
             Ok((
-                *c,
-                error_calc(&img_points, &font_points)
-                    .map_err(|e| format!("Error calculation failed: {e}"))?,
+                (grid, frame.delay, prepared.color),
+                (preparing, matching, comparison),
             ))
         })
-        .filter_map(std::result::Result::ok)
-        .min_by(|(_, t1), (_, t2)| t1.partial_cmp(t2).expect("comparison failed"))
-        .ok_or(String::from("unable to find minimum"))?
-        .0)
-}
+        .collect::<error::Result<_>>()?;
 
-/// Unicode image renderer
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None, disable_version_flag=true)]
-struct Args {
-    /// Input image
-    #[arg(short, long)]
-    image: String,
+    let mut fallbacks = error::Fallbacks::default();
+    let mut grids = Vec::with_capacity(frames.len());
 
-    /// Font to use during rendering
-    #[arg(short, long, default_value_t = String::from("mono"))]
-    font: String,
+    for (frame, (preparing, matching, comparison)) in frames {
+        timings.record("prepare", preparing);
+        timings.record("match", matching);
+        for line in comparison {
+            eprintln!("{line}");
+        }
 
-    /// Scale of unicode image
-    #[arg(short, long, default_value_t = 50)]
-    pixels_per_char: u8,
+        fallbacks.add(frame.0.fallbacks());
+        grids.push(frame);
+    }
 
-    /// similarity metric
-    #[arg(short, long, default_value_t, value_enum)]
-    similarity_metric: SimilarityMetric,
+    fallbacks.report(args.on_error, args.fallback_char);
 
-    // Verbose output
-    #[clap(short = 'V', long)]
-    verbose: bool,
+    Ok(grids)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+/// Renders an image file, or stdin when `image` is `-`, writing it to `output` or stdout
+fn render_image(
+    args: &Args,
+    renderer: &Renderer,
+    font_files: &[(Vec<u8>, u32)],
+    image: &str,
+    output: Option<&str>,
+    to_terminal: bool,
+    timings: &mut Timings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let layout = args.layout(to_terminal);
+    let mut frames = timings.time("decode", || animation::load_frames(image))?;
 
-    let img = image::open(&args.image)
-        .map_err(|_| format!("unable to open {}", args.image))?
-        .grayscale();
-    let font = font_utils::search_for_font(&args.font)?;
+    tracing::info!(frames = frames.len(), "decoded {image}");
 
-    if args.verbose {
-        println!("similarity metric {:?}", args.similarity_metric);
-        println!("font in use: {}", font.name().expect("font has no name"));
+    let diagnosing = args.diagnose || !args.inspect.is_empty();
+    if diagnosing || !matches!(args.format, output::OutputFormat::Text) {
+        frames.truncate(1);
+    }
 
-        print_to_console(&img.pixels(), img.width() as usize, |(_, _, p)| {
-            p.channels()[0] < 245
-        });
+    let grids = render_frames(args, renderer, frames, timings)?;
+
+    if diagnosing {
+        let diagnostics = diagnose::Diagnostics {
+            heatmap: args.diagnose,
+            worst: args.worst,
+            inspect: args.inspect.clone(),
+            color: to_terminal,
+        };
+
+        let mut out = output_writer(output)?;
+        if let Some((grid, _, _)) = grids.first() {
+            diagnose::write(grid, &diagnostics, &mut out)?;
+        }
+        out.flush()?;
+    } else {
+        timings.time("output", || {
+            write_frames(
+                args,
+                output,
+                &layout,
+                renderer,
+                font_files,
+                &grids,
+                to_terminal,
+            )
+        })?;
     }
 
-    let keep_partials = false;
+    Ok(())
+}
 
-    let sub_images = img_partitions_from(
-        &img,
-        args.pixels_per_char.into(),
-        args.pixels_per_char.into(),
-        keep_partials,
-    );
+/// Renders every image into `dir`, in parallel, reporting the images that failed
+fn render_batch(
+    args: &Args,
+    renderer: &Renderer,
+    font_files: &[(Vec<u8>, u32)],
+    images: &[PathBuf],
+    dir: &Path,
+    timings: &mut Timings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outputs = batch::output_paths(&args.output_name, images, dir, args.format.extension())?;
+    std::fs::create_dir_all(dir).map_err(|_| format!("unable to create {}", dir.display()))?;
 
-    let closest_chars: Vec<_> = sub_images
+    let results: Vec<_> = images
         .par_iter()
-        .map(|s| match args.similarity_metric {
-            SimilarityMetric::Hausdorff => match_char(s, &font, similarity::hausdorff_distance),
-            SimilarityMetric::Hamming => match_char(s, &font, similarity::hamming_distance),
-            SimilarityMetric::Levenshtein => match_char(s, &font, |p1, p2| {
-                Ok::<usize, Box<dyn std::error::Error>>(similarity::levenshtein_distance(p1, p2))
-            }),
+        .zip(&outputs)
+        .map(|(image, output)| {
+            let _image = tracing::info_span!("image", path = %image.display()).entered();
+            let mut timings = Timings::default();
+
+            render_image(
+                args,
+                renderer,
+                font_files,
+                &image.to_string_lossy(),
+                Some(&output.to_string_lossy()),
+                false,
+                &mut timings,
+            )
+            .map(|()| timings)
+            .map_err(|e| e.to_string())
         })
         .collect();
 
-    let rowsize = img.width() / u32::from(args.pixels_per_char) + u32::from(keep_partials);
+    let mut failed = 0;
+    for (image, result) in images.iter().zip(results) {
+        match result {
+            Ok(image_timings) => timings.merge(image_timings),
+            Err(e) => {
+                failed += 1;
+                tracing::error!("{}: {e}", image.display());
+            }
+        }
+    }
+
+    tracing::info!(images = images.len(), failed, "batch rendered");
+    if failed > 0 {
+        return Err(format!("{failed} of {} images failed", images.len()).into());
+    }
+
+    Ok(())
+}
+
+/// Loads `--font`, or without it the terminal's font when it can be found and `monospace`
+/// otherwise
+fn load_fonts(args: &Args) -> error::Result<Vec<font_utils::LoadedFont>> {
+    if args.font.is_empty() {
+        let selector = args.font_index.as_ref();
+        let detected = terminal_font::detect().and_then(|font| {
+            font_utils::search_for_font(&font, selector)
+                .inspect_err(|e| tracing::info!("Terminal font unavailable: {e}"))
+                .ok()
+        });
+
+        return match detected {
+            Some(font) => Ok(vec![font]),
+            None => Ok(vec![font_utils::search_for_font("mono", selector)?]),
+        };
+    }
+
+    args.font
+        .iter()
+        .enumerate()
+        .map(|(i, font)| {
+            font_utils::search_for_font(font, args.font_index.as_ref().filter(|_| i == 0))
+        })
+        .collect()
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::load()?;
+    logging::init(args.verbose, args.quiet);
+
+    if let Some(query) = &args.list_fonts {
+        return Ok(list_fonts(query)?);
+    }
+
+    let mut timings = Timings::default();
+
+    let loaded = timings.time("font", || load_fonts(&args))?;
+    let (fonts, font_files): (Vec<_>, Vec<_>) = loaded
+        .into_iter()
+        .map(|loaded| (loaded.font, (loaded.data, loaded.index)))
+        .unzip();
+
+    let settings = args.settings()?;
+    let renderer = timings.time("glyphs", || Renderer::new(fonts, settings));
+
+    tracing::info!(
+        metric = ?args.similarity_metric,
+        fonts = ?renderer.fonts().iter().map(|f| f.name().unwrap_or("unnamed")).collect::<Vec<_>>(),
+        "renderer ready"
+    );
+
+    let to_terminal = args.output.is_none() && std::io::stdout().is_terminal();
+    let layout = args.layout(to_terminal);
+
+    if let Some(frame_size) = args.frame_size {
+        if !matches!(args.format, output::OutputFormat::Text) {
+            return Err("video input only supports text output".into());
+        }
 
-    closest_chars
-        .chunks(rowsize as usize)
-        .map(|c| c.iter().map(|p| p.as_ref().unwrap_or(&' ')).join(""))
-        .for_each(|r| println!("| {r} |"));
+        return Ok(video::stream(
+            &renderer,
+            &layout,
+            frame_size,
+            std::io::stdin().lock(),
+            output_writer(args.output.as_deref())?,
+            to_terminal,
+        )?);
+    }
+
+    let images = batch::expand(&args.images)?;
+    match (&args.output_dir, images.as_slice()) {
+        (None, [image]) => render_image(
+            &args,
+            &renderer,
+            &font_files,
+            &image.to_string_lossy(),
+            args.output.as_deref(),
+            to_terminal,
+            &mut timings,
+        )?,
+        (Some(dir), _) => render_batch(
+            &args,
+            &renderer,
+            &font_files,
+            &images,
+            Path::new(dir),
+            &mut timings,
+        )?,
+        (None, _) => return Err("rendering several images needs --output-dir".into()),
+    }
+
+    for line in timings.report() {
+        tracing::info!(target: "derm_rs::timings", "{line}");
+    }
 
     Ok(())
 }
+
+fn main() -> std::process::ExitCode {
+    // errors are reported by their message rather than returned, which would show them as
+    // debug output
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
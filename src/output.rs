@@ -0,0 +1,318 @@
+use crate::layout::Layout;
+use derm_rs::glyphs::GlyphSource;
+use derm_rs::render::{Grid, Settings};
+
+use base64::Engine;
+use image::{DynamicImage, Rgb};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+mod html;
+mod json;
+mod png;
+mod svg;
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Plain text framed by the layout
+    #[default]
+    Text,
+
+    /// Self contained HTML page with colored characters and the embedded font
+    Html,
+
+    /// SVG placing every character exactly where it was matched
+    Svg,
+
+    /// PNG drawn with the matching font and cell size
+    Png,
+
+    /// Every cell with its score, runner up candidates and color, plus the settings used
+    Json,
+}
+
+impl OutputFormat {
+    /// Extension of files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Html => "html",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Png => "png",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// A font of the fallback chain along with the file it was loaded from
+pub struct FontFile<'a> {
+    pub font: &'a GlyphSource,
+    pub data: &'a [u8],
+
+    /// Face of `data` that `font` was loaded from, when it is a collection
+    pub index: u32,
+}
+
+/// A rendered grid and everything needed to write it out
+pub struct Document<'a> {
+    pub grid: &'a Grid,
+    pub layout: &'a Layout,
+
+    /// Fonts in fallback order, never empty
+    pub fonts: &'a [FontFile<'a>],
+
+    /// Width and height of every cell in pixels
+    pub cell_size: (u32, u32),
+    pub background: Rgb<u8>,
+    pub settings: &'a Settings,
+
+    /// Draw glyphs as paths rather than text in vector formats
+    pub outline_glyphs: bool,
+
+    /// Image to place next to the rendering in raster formats
+    pub source: Option<&'a DynamicImage>,
+}
+
+impl Document<'_> {
+    /// Position of the font supplying a character in the chain, the first one covering it
+    /// just like terminals fall back
+    pub fn font_position(&self, c: char) -> usize {
+        self.fonts
+            .iter()
+            .position(|f| f.font.has_glyph(c))
+            .unwrap_or(0)
+    }
+
+    pub fn font_for(&self, c: char) -> &FontFile<'_> {
+        &self.fonts[self.font_position(c)]
+    }
+}
+
+/// `@font-face` rules embedding every outline font of the chain as `derm0`, `derm1` and so
+/// on, followed by the `font-family` list using them in order. Browsers can't use bitmap
+/// fonts, so those are left to the generic `monospace`.
+fn font_faces(fonts: &[FontFile]) -> (String, String) {
+    let mut faces = String::new();
+    let mut families = String::new();
+
+    for (i, file) in fonts.iter().enumerate() {
+        if matches!(file.font, GlyphSource::Bitmap(_)) {
+            continue;
+        }
+
+        // browsers use the first face of a collection, not the one that was picked
+        let face = collection_face(file.data, file.index);
+        let data = face.as_deref().unwrap_or(file.data);
+        let (mime, format) = font_format(data);
+        let data = base64::engine::general_purpose::STANDARD.encode(data);
+        let _ = write!(
+            faces,
+            "@font-face {{ font-family: \"derm{i}\"; src: url(data:{mime};base64,{data}) format(\"{format}\"); }}"
+        );
+        let _ = write!(families, "\"derm{i}\", ");
+    }
+    families.push_str("monospace");
+
+    (faces, families)
+}
+
+/// Face `index` of a font collection as a font file of its own, its tables copied after a
+/// table directory pointing at them. `None` unless `data` is a collection holding the face.
+fn collection_face(data: &[u8], index: u32) -> Option<Vec<u8>> {
+    let u32_at = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?));
+    let usize_at = |at: usize| usize::try_from(u32_at(at)?).ok();
+
+    if data.get(..4)? != b"ttcf" || index >= u32_at(8)? {
+        return None;
+    }
+    let offset = usize_at(12 + 4 * usize::try_from(index).ok()?)?;
+    let num_tables = usize::from(u16::from_be_bytes(
+        data.get(offset + 4..offset + 6)?.try_into().ok()?,
+    ));
+    let directory_size = 12 + 16 * num_tables;
+
+    let mut face = data.get(offset..offset + 12)?.to_vec();
+    let mut tables = Vec::new();
+    for record in (offset + 12..offset + directory_size).step_by(16) {
+        let (table_offset, length) = (usize_at(record + 8)?, usize_at(record + 12)?);
+
+        // tag and checksum, then where the table now starts and its length
+        face.extend(data.get(record..record + 8)?);
+        face.extend(
+            u32::try_from(directory_size + tables.len())
+                .ok()?
+                .to_be_bytes(),
+        );
+        face.extend(data.get(record + 12..record + 16)?);
+
+        tables.extend(data.get(table_offset..table_offset + length)?);
+        tables.resize(tables.len().next_multiple_of(4), 0);
+    }
+    face.extend(tables);
+
+    Some(face)
+}
+
+/// Mime type and CSS `format()` hint of a font file
+fn font_format(data: &[u8]) -> (&'static str, &'static str) {
+    match data.get(..4) {
+        Some(b"OTTO") => ("font/otf", "opentype"),
+        Some(b"ttcf") => ("font/collection", "collection"),
+        Some(b"wOFF") => ("font/woff", "woff"),
+        Some(b"wOF2") => ("font/woff2", "woff2"),
+        _ => ("font/ttf", "truetype"),
+    }
+}
+
+fn hex_color(Rgb([r, g, b]): Rgb<u8>) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut escaped, c| {
+            match c {
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '&' => escaped.push_str("&amp;"),
+                '"' => escaped.push_str("&quot;"),
+                _ => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+pub fn write(format: OutputFormat, document: &Document, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            let mut text = String::new();
+            document.layout.write(&document.grid.text_rows(), &mut text);
+            out.write_all(text.as_bytes())
+        }
+        OutputFormat::Html => html::write(document, out),
+        OutputFormat::Svg => svg::write(document, out),
+        OutputFormat::Png => png::write(document, out),
+        OutputFormat::Json => json::write(document, out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derm_rs::bitmap_font::BitmapFont;
+    use derm_rs::render::Cell;
+
+    /// 2x2 bitmap font with a full `#` and a left column `|`
+    pub fn bitmap_font() -> GlyphSource {
+        let bdf = "STARTFONT 2.1\nFONTBOUNDINGBOX 2 2 0 0\n\
+            STARTCHAR full\nENCODING 35\nBBX 2 2 0 0\nBITMAP\nC0\nC0\nENDCHAR\n\
+            STARTCHAR left\nENCODING 124\nBBX 1 2 0 0\nBITMAP\n80\n80\nENDCHAR\n\
+            ENDFONT\n";
+        GlyphSource::Bitmap(BitmapFont::parse(bdf.as_bytes()).unwrap())
+    }
+
+    /// The system's `DejaVu Sans Mono`, installed on the CI runners, and its file contents
+    pub fn outline_font() -> (GlyphSource, Vec<u8>) {
+        let data = std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf")
+            .expect("tests need DejaVu Sans Mono");
+        let font = fontdue::Font::from_bytes(data.as_slice(), fontdue::FontSettings::default());
+
+        (GlyphSource::Outline(font.unwrap()), data)
+    }
+
+    pub fn cell(glyph: char, color: Rgb<u8>) -> Cell {
+        Cell {
+            glyph,
+            color,
+            score: None,
+            candidates: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Document of `grid` with a white background and nothing else set
+    pub fn document<'a>(
+        grid: &'a Grid,
+        layout: &'a Layout,
+        fonts: &'a [FontFile<'a>],
+        settings: &'a Settings,
+        cell_size: (u32, u32),
+    ) -> Document<'a> {
+        Document {
+            grid,
+            layout,
+            fonts,
+            cell_size,
+            background: Rgb([255, 255, 255]),
+            settings,
+            outline_glyphs: false,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn chars_map_to_the_first_font_covering_them() {
+        let (outline, data) = outline_font();
+        let bitmap = bitmap_font();
+        let fonts = [
+            FontFile {
+                font: &bitmap,
+                data: &[],
+                index: 0,
+            },
+            FontFile {
+                font: &outline,
+                data: &data,
+                index: 0,
+            },
+        ];
+        let (grid, layout, settings) = (
+            Grid::new(1, Vec::new()),
+            Layout::default(),
+            Settings::default(),
+        );
+        let document = document(&grid, &layout, &fonts, &settings, (2, 2));
+
+        // both fonts have `|` and `#`, only the outline font has `a`
+        let expected = vec![0, 0, 1, 0];
+        let result: Vec<_> = ['|', '#', 'a', '\u{10ffff}']
+            .into_iter()
+            .map(|c| document.font_position(c))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn collection_faces_stand_alone() {
+        // two faces with a single table each, the tables after both directories
+        let mut ttc = b"ttcf".to_vec();
+        for field in [0x0001_0000u32, 2, 20, 48] {
+            ttc.extend(field.to_be_bytes());
+        }
+        for (tag, offset, length) in [(b"aaaa", 76u32, 3u32), (b"bbbb", 80, 5)] {
+            ttc.extend([0, 1, 0, 0, 0, 1, 0, 16, 0, 0, 0, 0]);
+            ttc.extend(tag);
+            ttc.extend([0, 0, 0, 7]);
+            ttc.extend(offset.to_be_bytes());
+            ttc.extend(length.to_be_bytes());
+        }
+        ttc.extend([1, 2, 3, 0, 4, 5, 6, 7, 8]);
+
+        let mut expected = vec![0, 1, 0, 0, 0, 1, 0, 16, 0, 0, 0, 0];
+        expected.extend(b"bbbb");
+        expected.extend([0, 0, 0, 7, 0, 0, 0, 28, 0, 0, 0, 5]);
+        expected.extend([4, 5, 6, 7, 8, 0, 0, 0]);
+        let expected = (Some(expected), None);
+        let result = (collection_face(&ttc, 1), collection_face(&ttc, 2));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
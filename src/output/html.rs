@@ -0,0 +1,157 @@
+use super::{escape_xml, font_faces, hex_color, Document};
+use crate::layout::{Alignment, Border};
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Rows of the grid as `<span>`s, consecutive cells of the same color share a span and
+/// blank cells never start a new one
+fn colored_rows(document: &Document) -> String {
+    let mut html = String::new();
+
+    for row in document.grid.rows() {
+        let mut current = None;
+
+        for cell in row {
+            if current != Some(cell.color) && !cell.glyph.is_whitespace() {
+                if current.is_some() {
+                    html.push_str("</span>");
+                }
+                let _ = write!(html, "<span style=\"color:{}\">", hex_color(cell.color));
+                current = Some(cell.color);
+            }
+            html.push_str(&escape_xml(&cell.glyph.to_string()));
+        }
+
+        if current.is_some() {
+            html.push_str("</span>");
+        }
+        html.push('\n');
+    }
+
+    html
+}
+
+pub fn write(document: &Document, out: &mut impl Write) -> io::Result<()> {
+    let layout = document.layout;
+    let (font_faces, font_families) = font_faces(document.fonts);
+
+    let title = layout.title.as_deref().map(escape_xml);
+    let border = match layout.border {
+        Border::None => "border: none",
+        Border::Sides => "border-left: 1px solid; border-right: 1px solid",
+        Border::Ascii | Border::Unicode => "border: 1px solid",
+    };
+    let align = match layout.alignment {
+        Alignment::Left => "left",
+        Alignment::Center => "center",
+    };
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", title.as_deref().unwrap_or("derm"))?;
+    writeln!(out, "<style>")?;
+    writeln!(out, "{font_faces}")?;
+    writeln!(out, "body {{ text-align: {align}; }}")?;
+    writeln!(
+        out,
+        "pre {{ display: inline-block; text-align: left; font-family: {font_families}; line-height: 1; padding: 0.5em; {border}; background: {}; }}",
+        hex_color(document.background)
+    )?;
+    writeln!(out, "</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<figure>")?;
+    if let Some(title) = &title {
+        writeln!(out, "<h1>{title}</h1>")?;
+    }
+    write!(out, "<pre>{}</pre>", colored_rows(document))?;
+    if let Some(caption) = &layout.caption {
+        write!(out, "\n<figcaption>{}</figcaption>", escape_xml(caption))?;
+    }
+    writeln!(out, "\n</figure>")?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Layout;
+    use crate::output::tests::{bitmap_font, cell, document};
+    use crate::output::FontFile;
+    use derm_rs::render::{Grid, Settings};
+
+    use image::Rgb;
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+    const GREEN: Rgb<u8> = Rgb([0, 255, 0]);
+    const BLUE: Rgb<u8> = Rgb([0, 0, 255]);
+
+    fn html(grid: &Grid, layout: &Layout) -> (String, String) {
+        let font = bitmap_font();
+        let fonts = [FontFile {
+            font: &font,
+            data: &[],
+            index: 0,
+        }];
+        let settings = Settings::default();
+        let document = document(grid, layout, &fonts, &settings, (2, 2));
+
+        let mut page = Vec::new();
+        write(&document, &mut page).unwrap();
+        (colored_rows(&document), String::from_utf8(page).unwrap())
+    }
+
+    #[test]
+    fn spans_cover_runs_of_one_color() {
+        let grid = Grid::new(
+            4,
+            vec![
+                cell('a', RED),
+                cell('b', RED),
+                cell(' ', GREEN),
+                cell('c', RED),
+                cell(' ', GREEN),
+                cell('d', BLUE),
+                cell('e', RED),
+                cell(' ', BLUE),
+            ],
+        );
+
+        let expected = "<span style=\"color:#ff0000\">ab c</span>\n \
+            <span style=\"color:#0000ff\">d</span><span style=\"color:#ff0000\">e </span>\n";
+        let (result, _) = html(&grid, &Layout::default());
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn markup_is_escaped() {
+        let grid = Grid::new(3, vec![cell('<', RED), cell('&', RED), cell('"', RED)]);
+        let layout = Layout {
+            title: Some(String::from("<b> & \"c\"")),
+            ..Layout::default()
+        };
+
+        let expected = (
+            String::from("<span style=\"color:#ff0000\">&lt;&amp;&quot;</span>\n"),
+            true,
+        );
+        let (rows, page) = html(&grid, &layout);
+        let result = (
+            rows,
+            page.contains("<title>&lt;b&gt; &amp; &quot;c&quot;</title>"),
+        );
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::{hex_color, Document};
+use derm_rs::edges::EdgeDetector;
+use derm_rs::error::ErrorPolicy;
+use derm_rs::glyphs::Rasterization;
+use derm_rs::image_utils::AlphaMode;
+use derm_rs::render::{Candidate, SimilarityMetric};
+
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Serialize)]
+struct Settings<'a> {
+    fonts: Vec<Option<&'a str>>,
+    rasterization: Rasterization,
+    charset: Option<&'a str>,
+    similarity_metric: SimilarityMetric,
+    alpha: AlphaMode,
+    background: String,
+    preprocess: Vec<String>,
+    edges: Option<EdgeDetector>,
+    edge_threshold: f32,
+    edge_orientation: bool,
+    candidates: usize,
+    on_error: ErrorPolicy,
+    fallback_char: char,
+}
+
+#[derive(Serialize)]
+struct Cell<'a> {
+    row: usize,
+    column: usize,
+    #[serde(rename = "char")]
+    glyph: char,
+
+    /// Font of the fallback chain that supplied the char, none for blank cells
+    font: Option<&'a str>,
+
+    /// Error of the chosen char, lower is better
+    score: Option<f64>,
+    color: String,
+    candidates: &'a [Candidate],
+
+    /// Why the cell couldn't be matched and holds the fallback character
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Output<'a> {
+    columns: usize,
+    rows: usize,
+
+    /// Width and height
+    cell_size: (u32, u32),
+    settings: Settings<'a>,
+    text: Vec<String>,
+    cells: Vec<Cell<'a>>,
+}
+
+pub fn write(document: &Document, out: &mut impl Write) -> io::Result<()> {
+    let settings = document.settings;
+    let (columns, rows) = document.grid.dimensions();
+
+    let cells = document
+        .grid
+        .rows()
+        .enumerate()
+        .flat_map(|(row, cells)| {
+            cells.iter().enumerate().map(move |(column, cell)| Cell {
+                row,
+                column,
+                glyph: cell.glyph,
+                font: (!cell.glyph.is_whitespace())
+                    .then(|| document.font_for(cell.glyph).font.name())
+                    .flatten(),
+                score: cell.score,
+                color: hex_color(cell.color),
+                candidates: &cell.candidates,
+                error: cell.error.as_ref().map(ToString::to_string),
+            })
+        })
+        .collect();
+
+    let output = Output {
+        columns,
+        rows,
+        cell_size: document.cell_size,
+        settings: Settings {
+            fonts: document.fonts.iter().map(|f| f.font.name()).collect(),
+            rasterization: settings.rasterization,
+            charset: settings.charset.as_deref(),
+            similarity_metric: settings.similarity_metric,
+            alpha: settings.alpha,
+            background: hex_color(settings.background),
+            preprocess: settings
+                .operations
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            edges: settings.edges,
+            edge_threshold: settings.edge_threshold,
+            edge_orientation: settings.edge_orientation,
+            candidates: settings.candidates,
+            on_error: settings.on_error,
+            fallback_char: settings.fallback_char,
+        },
+        text: document.grid.text_rows(),
+        cells,
+    };
+
+    serde_json::to_writer_pretty(&mut *out, &output)?;
+    writeln!(out)
+}
@@ -0,0 +1,138 @@
+use super::Document;
+
+use image::codecs::png::PngEncoder;
+use image::{imageops, DynamicImage, ImageEncoder, Rgb, RgbImage};
+use std::io::{self, Write};
+
+fn blend(background: Rgb<u8>, foreground: Rgb<u8>, coverage: u8) -> Rgb<u8> {
+    let (a, inverse) = (u16::from(coverage), 255 - u16::from(coverage));
+    Rgb(std::array::from_fn(|i| {
+        let value = (u16::from(foreground[i]) * a + u16::from(background[i]) * inverse + 127) / 255;
+        u8::try_from(value).unwrap_or(u8::MAX)
+    }))
+}
+
+/// Draws the grid with the matching font, cell size and rasterization, each glyph placed in
+/// its cell just as it was compared against the image
+pub fn rasterize(document: &Document) -> RgbImage {
+    let (columns, rows) = document.grid.dimensions();
+    let (cell_width, cell_height) = document.cell_size;
+    let to_u32 = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+
+    let mut img = RgbImage::from_pixel(
+        to_u32(columns) * cell_width,
+        to_u32(rows) * cell_height,
+        document.background,
+    );
+
+    for (row, cells) in document.grid.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            if cell.glyph.is_whitespace() {
+                continue;
+            }
+
+            let font = document.font_for(cell.glyph).font;
+            let bitmap = font.coverage(
+                cell.glyph,
+                document.cell_size,
+                &document.settings.rasterization,
+            );
+            let (x0, y0) = (to_u32(column) * cell_width, to_u32(row) * cell_height);
+            let width = (cell_width as usize).max(1);
+
+            for (i, coverage) in bitmap.iter().enumerate() {
+                let x = x0 + to_u32(i % width);
+                let y = y0 + to_u32(i / width);
+
+                if *coverage > 0 && x < img.width() && y < img.height() {
+                    let pixel = img.get_pixel_mut(x, y);
+                    *pixel = blend(*pixel, cell.color, *coverage);
+                }
+            }
+        }
+    }
+
+    img
+}
+
+/// The source image next to its rendering, cropped to the cells that were matched
+fn side_by_side(source: &DynamicImage, rendered: &RgbImage, background: Rgb<u8>) -> RgbImage {
+    let (width, height) = rendered.dimensions();
+    let source = source.crop_imm(0, 0, width, height).to_rgba8();
+
+    let mut img = RgbImage::from_pixel(width * 2, height, background);
+    for (x, y, p) in source.enumerate_pixels() {
+        img.put_pixel(x, y, blend(background, Rgb([p[0], p[1], p[2]]), p[3]));
+    }
+    imageops::replace(&mut img, rendered, i64::from(width), 0);
+
+    img
+}
+
+pub fn write(document: &Document, out: &mut impl Write) -> io::Result<()> {
+    let rendered = rasterize(document);
+    let img = match document.source {
+        Some(source) => side_by_side(source, &rendered, document.background),
+        None => rendered,
+    };
+
+    PngEncoder::new(out)
+        .write_image(
+            img.as_raw(),
+            img.width(),
+            img.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Layout;
+    use crate::output::tests::{bitmap_font, cell, document};
+    use crate::output::FontFile;
+    use derm_rs::render::{Grid, Settings};
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+    const BLUE: Rgb<u8> = Rgb([0, 0, 255]);
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+    fn rendered() -> RgbImage {
+        let font = bitmap_font();
+        let fonts = [FontFile {
+            font: &font,
+            data: &[],
+            index: 0,
+        }];
+        let grid = Grid::new(2, vec![cell('|', RED), cell(' ', BLUE)]);
+        let (layout, settings) = (Layout::default(), Settings::default());
+
+        rasterize(&document(&grid, &layout, &fonts, &settings, (2, 2)))
+    }
+
+    #[test]
+    fn glyphs_are_drawn_in_their_cell() {
+        let expected = vec![RED, WHITE, WHITE, WHITE, RED, WHITE, WHITE, WHITE];
+        let result: Vec<_> = rendered().pixels().copied().collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn source_is_cropped_beside_the_rendering() {
+        let source = DynamicImage::ImageRgb8(RgbImage::from_pixel(5, 3, BLUE));
+        let img = side_by_side(&source, &rendered(), WHITE);
+
+        let expected = ((8, 2), BLUE, RED);
+        let result = (img.dimensions(), *img.get_pixel(3, 1), *img.get_pixel(4, 1));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
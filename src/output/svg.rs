@@ -0,0 +1,346 @@
+use super::{escape_xml, font_faces, hex_color, Document};
+use crate::layout::Border;
+use derm_rs::glyphs::{GlyphSource, Placement};
+use derm_rs::render::Cell;
+use derm_rs::similarity::Points;
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Builds SVG path data for a glyph outline, mapping font units onto the cell
+struct PathBuilder {
+    data: String,
+    scale: f32,
+    x: f32,
+    baseline: f32,
+}
+
+impl PathBuilder {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + x * self.scale, self.baseline - y * self.scale)
+    }
+}
+
+impl ttf_parser::OutlineBuilder for PathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.data, "M{x:.2} {y:.2}");
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.data, "L{x:.2} {y:.2}");
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.data, "Q{x1:.2} {y1:.2} {x:.2} {y:.2}");
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x2, y2) = self.point(x2, y2);
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.data, "C{x1:.2} {y1:.2} {x2:.2} {y2:.2} {x:.2} {y:.2}");
+    }
+
+    fn close(&mut self) {
+        self.data.push('Z');
+    }
+}
+
+/// Path data drawing every pixel of a bitmap glyph as a square
+fn pixels(points: &Points, x: f32, y: f32) -> String {
+    let mut points: Vec<_> = points.iter().collect();
+    points.sort_unstable_by_key(|(x, y)| (*y, *x));
+
+    points
+        .into_iter()
+        .fold(String::new(), |mut data, (px, py)| {
+            let _ = write!(
+                data,
+                "M{} {}h1v1h-1Z",
+                x + f32::from(*px),
+                y + f32::from(*py)
+            );
+            data
+        })
+}
+
+/// Path or text drawing a cell's glyph, whose cell starts at `(x0, y0)`
+fn glyph_element(
+    document: &Document,
+    faces: &[Option<ttf_parser::Face>],
+    cell: &Cell,
+    (x0, y0): (f32, f32),
+) -> Option<String> {
+    let fill = hex_color(cell.color);
+    let font = document.font_position(cell.glyph);
+
+    let outline = match document.fonts[font].font {
+        GlyphSource::Outline(outline) => outline,
+        GlyphSource::Bitmap(bitmap) => {
+            let data = pixels(bitmap.glyph(cell.glyph)?, x0, y0);
+            return Some(format!("<path d=\"{data}\" fill=\"{fill}\"/>"));
+        }
+    };
+
+    // the glyph is placed where the bitmap it was matched with landed in the cell
+    let cell_height = document.cell_size.1;
+    let placement = Placement::new(
+        outline,
+        cell.glyph,
+        cell_height,
+        document.settings.rasterization.scale,
+    );
+    let metrics = outline.metrics(cell.glyph, placement.px);
+    #[allow(clippy::cast_precision_loss)]
+    let x = x0 + (placement.x - metrics.xmin) as f32;
+    #[allow(clippy::cast_precision_loss)]
+    let baseline = y0 + (placement.y + metrics.ymin) as f32 + metrics.height as f32;
+
+    if let Some(face) = faces.get(font).and_then(Option::as_ref) {
+        let mut builder = PathBuilder {
+            data: String::new(),
+            scale: placement.px / f32::from(face.units_per_em()),
+            x,
+            baseline,
+        };
+
+        face.outline_glyph(face.glyph_index(cell.glyph)?, &mut builder);
+        return (!builder.data.is_empty())
+            .then(|| format!("<path d=\"{}\" fill=\"{fill}\"/>", builder.data));
+    }
+
+    // glyphs scaled to fit the cell aren't at the stylesheet's font size
+    #[allow(clippy::cast_precision_loss)]
+    let size = if (placement.px - cell_height as f32).abs() > f32::EPSILON {
+        format!(" font-size=\"{:.2}px\"", placement.px)
+    } else {
+        String::new()
+    };
+    Some(format!(
+        "<text x=\"{x:.2}\" y=\"{baseline:.2}\"{size} fill=\"{fill}\">{}</text>",
+        escape_xml(&cell.glyph.to_string())
+    ))
+}
+
+pub fn write(document: &Document, out: &mut impl Write) -> io::Result<()> {
+    let (columns, rows) = document.grid.dimensions();
+    #[allow(clippy::cast_precision_loss)]
+    let (cell_width, cell_height) = (document.cell_size.0 as f32, document.cell_size.1 as f32);
+    #[allow(clippy::cast_precision_loss)]
+    let (width, height) = (columns as f32 * cell_width, rows as f32 * cell_height);
+
+    // bitmap fonts have no outlines, their glyphs are always drawn pixel by pixel
+    let faces = if document.outline_glyphs {
+        document
+            .fonts
+            .iter()
+            .map(|file| match file.font {
+                GlyphSource::Outline(_) => ttf_parser::Face::parse(file.data, file.index).map(Some),
+                GlyphSource::Bitmap(_) => Ok(None),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+    } else {
+        Vec::new()
+    };
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    )?;
+    if let Some(title) = &document.layout.title {
+        writeln!(out, "<title>{}</title>", escape_xml(title))?;
+    }
+    if let Some(caption) = &document.layout.caption {
+        writeln!(out, "<desc>{}</desc>", escape_xml(caption))?;
+    }
+    if !document.outline_glyphs {
+        let (font_faces, font_families) = font_faces(document.fonts);
+        writeln!(
+            out,
+            "<style>{font_faces} text {{ font-family: {font_families}; font-size: {cell_height}px; white-space: pre; }}</style>"
+        )?;
+    }
+
+    let stroke = match document.layout.border {
+        Border::None | Border::Sides => String::new(),
+        Border::Ascii | Border::Unicode => String::from(" stroke=\"#000000\""),
+    };
+    writeln!(
+        out,
+        "<rect width=\"{width}\" height=\"{height}\" fill=\"{}\"{stroke}/>",
+        hex_color(document.background)
+    )?;
+    if document.layout.border == Border::Sides {
+        writeln!(
+            out,
+            "<path d=\"M0 0V{height}M{width} 0V{height}\" stroke=\"#000000\"/>"
+        )?;
+    }
+
+    for (row, cells) in document.grid.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            if cell.glyph.is_whitespace() {
+                continue;
+            }
+
+            #[allow(clippy::cast_precision_loss)]
+            let (x0, y0) = (column as f32 * cell_width, row as f32 * cell_height);
+            if let Some(element) = glyph_element(document, &faces, cell, (x0, y0)) {
+                writeln!(out, "{element}")?;
+            }
+        }
+    }
+
+    writeln!(out, "</svg>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Layout;
+    use crate::output::tests::{bitmap_font, cell, document, outline_font};
+    use crate::output::FontFile;
+    use derm_rs::glyphs::ScalePolicy;
+    use derm_rs::render::{Grid, Settings};
+
+    use image::Rgb;
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+    #[test]
+    fn outline_glyphs_are_paths() {
+        let (font, data) = outline_font();
+        let fonts = [FontFile {
+            font: &font,
+            data: &data,
+            index: 0,
+        }];
+        let (grid, layout, settings) = (
+            Grid::new(1, Vec::new()),
+            Layout::default(),
+            Settings::default(),
+        );
+        let document = document(&grid, &layout, &fonts, &settings, (20, 20));
+        let faces = [Some(ttf_parser::Face::parse(&data, 0).unwrap())];
+
+        let element = glyph_element(&document, &faces, &cell('l', RED), (0.0, 0.0)).unwrap();
+
+        let expected = (true, true);
+        let result = (
+            element.starts_with("<path d=\"M"),
+            element.ends_with("Z\" fill=\"#ff0000\"/>"),
+        );
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn text_is_sized_like_the_matched_glyph() {
+        let (font, data) = outline_font();
+        let fonts = [FontFile {
+            font: &font,
+            data: &data,
+            index: 0,
+        }];
+        let GlyphSource::Outline(outline) = &font else {
+            unreachable!()
+        };
+        let (grid, layout) = (Grid::new(1, Vec::new()), Layout::default());
+        let scaled = Placement::new(outline, 'l', 20, ScalePolicy::CellHeight).px;
+
+        let expected = vec![
+            (true, String::new()),
+            (true, format!(" font-size=\"{scaled:.2}px\"")),
+        ];
+        let result: Vec<_> = [ScalePolicy::Em, ScalePolicy::CellHeight]
+            .into_iter()
+            .map(|scale| {
+                let mut settings = Settings::default();
+                settings.rasterization.scale = scale;
+                let document = document(&grid, &layout, &fonts, &settings, (20, 20));
+                let element = glyph_element(&document, &[], &cell('l', RED), (0.0, 0.0)).unwrap();
+
+                let size = element
+                    .find(" font-size")
+                    .map(|start| element[start..].split_inclusive("px\"").next().unwrap())
+                    .unwrap_or_default()
+                    .to_string();
+                (
+                    element.starts_with("<text ") && element.ends_with(">l</text>"),
+                    size,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn bitmap_glyphs_are_pixels() {
+        let font = bitmap_font();
+        let fonts = [FontFile {
+            font: &font,
+            data: &[],
+            index: 0,
+        }];
+        let (grid, layout, settings) = (
+            Grid::new(1, Vec::new()),
+            Layout::default(),
+            Settings::default(),
+        );
+        let document = document(&grid, &layout, &fonts, &settings, (2, 2));
+
+        let expected = Some(String::from(
+            "<path d=\"M2 4h1v1h-1ZM2 5h1v1h-1Z\" fill=\"#ff0000\"/>",
+        ));
+        let result = glyph_element(&document, &[], &cell('|', RED), (2.0, 4.0));
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn sides_border_is_two_lines() {
+        let font = bitmap_font();
+        let fonts = [FontFile {
+            font: &font,
+            data: &[],
+            index: 0,
+        }];
+        let grid = Grid::new(2, vec![cell('#', RED), cell(' ', RED)]);
+        let (layout, settings) = (Layout::default(), Settings::default());
+        let document = document(&grid, &layout, &fonts, &settings, (2, 2));
+
+        let mut svg = Vec::new();
+        write(&document, &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        let expected = vec![
+            "<rect width=\"4\" height=\"2\" fill=\"#ffffff\"/>",
+            "<path d=\"M0 0V2M4 0V2\" stroke=\"#000000\"/>",
+            "<path d=\"M0 0h1v1h-1ZM1 0h1v1h-1ZM0 1h1v1h-1ZM1 1h1v1h-1Z\" fill=\"#ff0000\"/>",
+        ];
+        let result: Vec<_> = svg
+            .lines()
+            .filter(|line| line.starts_with("<rect") || line.starts_with("<path"))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
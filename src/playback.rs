@@ -0,0 +1,283 @@
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue, terminal,
+};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+
+/// Delays up to this long are treated as unset, as browsers do
+const MIN_DELAY: Duration = Duration::from_millis(10);
+
+/// Delay of frames whose delay is unset
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// A run of cells that changed between two frames, starting at a terminal column
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub column: usize,
+    pub text: String,
+}
+
+/// Owns the terminal for the duration of playback, restoring it when dropped even if
+/// playback is interrupted
+struct Terminal {
+    stdout: io::Stdout,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+
+        Ok(Terminal { stdout })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+enum Command {
+    Quit,
+    TogglePause,
+}
+
+fn command_from(event: &Event) -> Option<Command> {
+    match event {
+        Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) => match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(Command::Quit),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Command::Quit),
+            KeyCode::Char(' ' | 'p') => Some(Command::TogglePause),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn columns(row: &str) -> Vec<(usize, char)> {
+    row.chars()
+        .scan(0, |column, c| {
+            let start = *column;
+            *column += c.width().unwrap_or(0);
+            Some((start, c))
+        })
+        .collect()
+}
+
+/// Runs of cells in `new` that must be redrawn over `old`, a cell is redrawn when its
+/// character or its column changed
+pub fn diff_row(old: &str, new: &str) -> Vec<Run> {
+    let old = columns(old);
+    let new = columns(new);
+
+    let mut runs: Vec<Run> = Vec::new();
+    let mut run_end = None;
+
+    for (i, &(column, c)) in new.iter().enumerate() {
+        if old.get(i) == Some(&(column, c)) {
+            continue;
+        }
+
+        match runs.last_mut() {
+            Some(run) if run_end == Some(i) => run.text.push(c),
+            _ => runs.push(Run {
+                column,
+                text: c.to_string(),
+            }),
+        }
+        run_end = Some(i + 1);
+    }
+
+    runs
+}
+
+fn row_width(row: &str) -> usize {
+    row.chars().filter_map(UnicodeWidthChar::width).sum()
+}
+
+fn draw(stdout: &mut io::Stdout, previous: &[String], rows: &[String]) -> io::Result<()> {
+    for (y, row) in rows.iter().enumerate() {
+        let old = previous.get(y).map_or("", String::as_str);
+        let y = u16::try_from(y).unwrap_or(u16::MAX);
+
+        for run in diff_row(old, row) {
+            let x = u16::try_from(run.column).unwrap_or(u16::MAX);
+            queue!(
+                stdout,
+                cursor::MoveTo(x, y),
+                crossterm::style::Print(run.text)
+            )?;
+        }
+
+        if row_width(old) > row_width(row) {
+            let x = u16::try_from(row_width(row)).unwrap_or(u16::MAX);
+            queue!(
+                stdout,
+                cursor::MoveTo(x, y),
+                terminal::Clear(terminal::ClearType::UntilNewLine)
+            )?;
+        }
+    }
+
+    for y in rows.len()..previous.len() {
+        let y = u16::try_from(y).unwrap_or(u16::MAX);
+        queue!(
+            stdout,
+            cursor::MoveTo(0, y),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )?;
+    }
+
+    stdout.flush()
+}
+
+/// How long a frame is shown, frames without a meaningful delay would otherwise be redrawn
+/// as fast as possible
+fn frame_delay(delay: Duration) -> Duration {
+    if delay <= MIN_DELAY {
+        DEFAULT_DELAY
+    } else {
+        delay
+    }
+}
+
+/// Plays frames of lines on the alternate screen, each shown for its delay. Space pauses,
+/// `q`, Escape and Ctrl-C quit.
+pub fn play(frames: &[(Vec<String>, Duration)], looping: bool) -> io::Result<()> {
+    let mut terminal = Terminal::enter()?;
+    let mut previous: &[String] = &[];
+    let mut paused = false;
+
+    loop {
+        for (rows, delay) in frames {
+            draw(&mut terminal.stdout, previous, rows)?;
+            previous = rows;
+
+            // time left to show the frame, kept while paused so it resumes where it stopped
+            let mut remaining = frame_delay(*delay);
+            loop {
+                let event = if paused {
+                    Some(event::read()?)
+                } else {
+                    let start = Instant::now();
+                    let polled = event::poll(remaining)?;
+                    remaining = remaining.saturating_sub(start.elapsed());
+                    if polled {
+                        Some(event::read()?)
+                    } else {
+                        None
+                    }
+                };
+
+                match event.as_ref().and_then(command_from) {
+                    Some(Command::Quit) => return Ok(()),
+                    Some(Command::TogglePause) => paused = !paused,
+                    None if event.is_none() || remaining.is_zero() => break,
+                    None => {}
+                }
+            }
+        }
+
+        if !looping {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_rows() {
+        let expected: Vec<Run> = vec![];
+        let result = diff_row("| abc |", "| abc |");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn separate_changes() {
+        let expected = vec![
+            Run {
+                column: 2,
+                text: String::from("x"),
+            },
+            Run {
+                column: 4,
+                text: String::from("yz"),
+            },
+        ];
+        let result = diff_row("| abcde |", "| xbyze |");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn width_change_shifts_following_cells() {
+        let expected = vec![Run {
+            column: 2,
+            text: String::from("♊b |"),
+        }];
+        let result = diff_row("| ab |", "| ♊b |");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn unset_delays_are_clamped() {
+        let expected = vec![
+            Duration::from_millis(100),
+            Duration::from_millis(100),
+            Duration::from_millis(20),
+        ];
+        let result: Vec<_> = [0, 10, 20]
+            .into_iter()
+            .map(|ms| frame_delay(Duration::from_millis(ms)))
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn longer_row() {
+        let expected = vec![Run {
+            column: 3,
+            text: String::from("d"),
+        }];
+        let result = diff_row("abc", "abcd");
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use image::{imageops, DynamicImage, GenericImageView, GrayImage, LumaA};
+use std::str::FromStr;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    UnknownOperation(String),
+    MissingArgument(String),
+    InvalidArgument(String, String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownOperation(op) => write!(f, "Unknown preprocessing operation: {op}"),
+            Error::MissingArgument(op) => write!(f, "Operation '{op}' requires an argument"),
+            Error::InvalidArgument(op, arg) => {
+                write!(f, "Invalid argument '{arg}' for operation '{op}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+}
+
+/// A single image transformation, written on the command line as `name=argument`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    /// `resize=WxH`, `resize=Wx` or `resize=xH`, the latter two preserve aspect ratio
+    Resize(Option<u32>, Option<u32>),
+
+    /// `crop=WxH+X+Y`
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+
+    /// `rotate=90`, `rotate=180` or `rotate=270` (clockwise)
+    Rotate(u16),
+
+    /// `flip=h` or `flip=v`
+    Flip(Flip),
+
+    /// `brightness=N`, added to every channel
+    Brightness(i32),
+
+    /// `contrast=N`, in percent
+    Contrast(f32),
+
+    /// `gamma=N`, values above 1 brighten midtones
+    Gamma(f32),
+
+    /// `unsharp=SIGMA` or `unsharp=SIGMA,THRESHOLD`
+    Unsharpen(f32, i32),
+
+    /// `blur=SIGMA`
+    Blur(f32),
+
+    /// `equalize`, histogram equalization of the luma channel
+    Equalize,
+}
+
+fn parse_arg<T: FromStr>(op: &str, arg: &str) -> Result<T> {
+    arg.trim()
+        .parse()
+        .map_err(|_| Error::InvalidArgument(op.to_string(), arg.to_string()))
+}
+
+fn parse_dimensions(op: &str, arg: &str) -> Result<(Option<u32>, Option<u32>)> {
+    let (w, h) = arg
+        .split_once('x')
+        .ok_or_else(|| Error::InvalidArgument(op.to_string(), arg.to_string()))?;
+
+    let parse_optional = |s: &str| -> Result<Option<u32>> {
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            parse_arg(op, s).map(Some)
+        }
+    };
+
+    match (parse_optional(w)?, parse_optional(h)?) {
+        (None, None) | (Some(0), _) | (_, Some(0)) => {
+            Err(Error::InvalidArgument(op.to_string(), arg.to_string()))
+        }
+        dims => Ok(dims),
+    }
+}
+
+/// Whether a gaussian blur can be computed with `sigma`
+fn is_sigma(sigma: f32) -> bool {
+    sigma.is_finite() && sigma > 0.0
+}
+
+impl FromStr for Operation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, arg) = match s.trim().split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+
+        let required = || arg.ok_or_else(|| Error::MissingArgument(name.to_string()));
+        let invalid = |arg: &str| Error::InvalidArgument(name.to_string(), arg.to_string());
+
+        match name {
+            "resize" => {
+                let (width, height) = parse_dimensions(name, required()?)?;
+                Ok(Operation::Resize(width, height))
+            }
+            "crop" => {
+                let arg = required()?;
+                let (dims, offsets) = arg.split_once('+').ok_or_else(|| invalid(arg))?;
+                let (x, y) = offsets.split_once('+').ok_or_else(|| invalid(arg))?;
+
+                match parse_dimensions(name, dims)? {
+                    (Some(width), Some(height)) => Ok(Operation::Crop {
+                        x: parse_arg(name, x)?,
+                        y: parse_arg(name, y)?,
+                        width,
+                        height,
+                    }),
+                    _ => Err(invalid(arg)),
+                }
+            }
+            "rotate" => match parse_arg(name, required()?)? {
+                degrees @ (90 | 180 | 270) => Ok(Operation::Rotate(degrees)),
+                _ => Err(invalid(required()?)),
+            },
+            "flip" => match required()? {
+                "h" | "horizontal" => Ok(Operation::Flip(Flip::Horizontal)),
+                "v" | "vertical" => Ok(Operation::Flip(Flip::Vertical)),
+                arg => Err(invalid(arg)),
+            },
+            "brightness" => Ok(Operation::Brightness(parse_arg(name, required()?)?)),
+            "contrast" => Ok(Operation::Contrast(parse_arg(name, required()?)?)),
+            "gamma" => match parse_arg(name, required()?)? {
+                gamma if gamma > 0.0 => Ok(Operation::Gamma(gamma)),
+                _ => Err(invalid(required()?)),
+            },
+            "unsharp" => {
+                let arg = required()?;
+                let (sigma, threshold) = match arg.split_once(',') {
+                    Some((sigma, threshold)) => {
+                        (parse_arg(name, sigma)?, parse_arg(name, threshold)?)
+                    }
+                    None => (parse_arg(name, arg)?, 0),
+                };
+                match sigma {
+                    sigma if is_sigma(sigma) => Ok(Operation::Unsharpen(sigma, threshold)),
+                    _ => Err(invalid(arg)),
+                }
+            }
+            "blur" => match parse_arg(name, required()?)? {
+                sigma if is_sigma(sigma) => Ok(Operation::Blur(sigma)),
+                _ => Err(invalid(required()?)),
+            },
+            "equalize" => Ok(Operation::Equalize),
+            _ => Err(Error::UnknownOperation(name.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dimension = |d: &Option<u32>| d.map(|d| d.to_string()).unwrap_or_default();
+
+        match self {
+            Operation::Resize(width, height) => {
+                write!(f, "resize={}x{}", dimension(width), dimension(height))
+            }
+            Operation::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => write!(f, "crop={width}x{height}+{x}+{y}"),
+            Operation::Rotate(degrees) => write!(f, "rotate={degrees}"),
+            Operation::Flip(Flip::Horizontal) => write!(f, "flip=h"),
+            Operation::Flip(Flip::Vertical) => write!(f, "flip=v"),
+            Operation::Brightness(n) => write!(f, "brightness={n}"),
+            Operation::Contrast(n) => write!(f, "contrast={n}"),
+            Operation::Gamma(n) => write!(f, "gamma={n}"),
+            Operation::Unsharpen(sigma, threshold) => write!(f, "unsharp={sigma},{threshold}"),
+            Operation::Blur(sigma) => write!(f, "blur={sigma}"),
+            Operation::Equalize => write!(f, "equalize"),
+        }
+    }
+}
+
+/// Reads operations from a file containing one operation per line, `#` starts a comment
+///
+/// # Errors
+///
+/// When the file can't be read or any of its lines isn't a valid operation
+pub fn operations_from_file(path: &str) -> crate::error::Result<Vec<Operation>> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|_| crate::error::Error::Open(path.to_string()))?;
+
+    Ok(contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<Operation>>>()?)
+}
+
+fn gamma_lut(gamma: f32) -> [u8; 256] {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    std::array::from_fn(|v| (255.0 * (v as f32 / 255.0).powf(1.0 / gamma)).round() as u8)
+}
+
+fn gamma(img: &DynamicImage, gamma: f32) -> DynamicImage {
+    let lut = gamma_lut(gamma);
+
+    let mut rgba = img.to_rgba8();
+    rgba.pixels_mut().for_each(|p| {
+        p.0[..3].iter_mut().for_each(|c| *c = lut[*c as usize]);
+    });
+
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Mapping spreading the luma values of a histogram over the full range, `None` when every
+/// pixel has the same luma
+fn equalize_lut(histogram: &[u64; 256]) -> Option<[u8; 256]> {
+    let mut cdf = [0u64; 256];
+    let mut total = 0;
+    for (c, count) in cdf.iter_mut().zip(histogram) {
+        total += count;
+        *c = total;
+    }
+
+    let cdf_min = cdf.iter().copied().find(|&c| c > 0).unwrap_or_default();
+    if total == cdf_min {
+        return None;
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    Some(cdf.map(|c| {
+        (c.saturating_sub(cdf_min) as f64 / (total - cdf_min) as f64 * 255.0).round() as u8
+    }))
+}
+
+fn equalize(img: &DynamicImage) -> DynamicImage {
+    let mut luma = img.to_luma_alpha8();
+
+    let mut histogram = [0u64; 256];
+    luma.pixels().for_each(|p| histogram[p.0[0] as usize] += 1);
+
+    if let Some(lut) = equalize_lut(&histogram) {
+        luma.pixels_mut()
+            .for_each(|p| *p = LumaA([lut[p.0[0] as usize], p.0[1]]));
+    }
+
+    DynamicImage::ImageLumaA8(luma)
+}
+
+impl Operation {
+    #[must_use]
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        match *self {
+            Operation::Resize(width, height) => {
+                let (img_width, img_height) = img.dimensions();
+                let filter = image::imageops::FilterType::Lanczos3;
+
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let scale = |len: u32, num: u32, den: u32| -> u32 {
+                    ((f64::from(len) * f64::from(num) / f64::from(den)).round() as u32).max(1)
+                };
+
+                match (width, height) {
+                    (Some(w), Some(h)) => img.resize_exact(w, h, filter),
+                    (Some(w), None) => img.resize_exact(w, scale(img_height, w, img_width), filter),
+                    (None, Some(h)) => img.resize_exact(scale(img_width, h, img_height), h, filter),
+                    (None, None) => img,
+                }
+            }
+            Operation::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => img.crop_imm(x, y, width, height),
+            Operation::Rotate(90) => img.rotate90(),
+            Operation::Rotate(180) => img.rotate180(),
+            Operation::Rotate(270) => img.rotate270(),
+            Operation::Rotate(_) => img,
+            Operation::Flip(Flip::Horizontal) => img.fliph(),
+            Operation::Flip(Flip::Vertical) => img.flipv(),
+            Operation::Brightness(value) => img.brighten(value),
+            Operation::Contrast(value) => img.adjust_contrast(value),
+            Operation::Gamma(value) => gamma(&img, value),
+            Operation::Unsharpen(sigma, threshold) => img.unsharpen(sigma, threshold),
+            Operation::Blur(sigma) => img.blur(sigma),
+            Operation::Equalize => equalize(&img),
+        }
+    }
+}
+
+impl Operation {
+    /// Applies a per pixel operation to a grayscale image in place, `false` for operations
+    /// that produce a new image
+    fn apply_in_place(&self, img: &mut GrayImage) -> bool {
+        match *self {
+            Operation::Brightness(value) => imageops::colorops::brighten_in_place(img, value),
+            Operation::Contrast(value) => imageops::colorops::contrast_in_place(img, value),
+            Operation::Gamma(value) => {
+                let lut = gamma_lut(value);
+                img.iter_mut().for_each(|p| *p = lut[usize::from(*p)]);
+            }
+            Operation::Equalize => {
+                let mut histogram = [0u64; 256];
+                img.iter().for_each(|p| histogram[usize::from(*p)] += 1);
+                if let Some(lut) = equalize_lut(&histogram) {
+                    img.iter_mut().for_each(|p| *p = lut[usize::from(*p)]);
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// Applies every operation in order to a grayscale image, per pixel operations in place and
+/// those changing its geometry or sharpness into a new image
+pub fn apply_gray(img: &mut GrayImage, operations: &[Operation]) {
+    for op in operations {
+        if !op.apply_in_place(img) {
+            *img = op
+                .apply(DynamicImage::ImageLuma8(std::mem::take(img)))
+                .into_luma8();
+        }
+    }
+}
+
+/// Applies every operation in order
+#[must_use]
+pub fn apply(img: DynamicImage, operations: &[Operation]) -> DynamicImage {
+    operations.iter().fold(img, |img, op| op.apply(img))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn parse_resize() {
+        let expected = Ok(Operation::Resize(Some(200), None));
+        let result = "resize=200x".parse::<Operation>();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_crop_geometry() {
+        let expected = Ok(Operation::Crop {
+            x: 5,
+            y: 10,
+            width: 100,
+            height: 50,
+        });
+        let result = "crop=100x50+5+10".parse::<Operation>();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let expected = vec!["resize=x40", "crop=100x50+5+10", "flip=h", "unsharp=1.5,2"];
+        let result: Vec<String> = expected
+            .iter()
+            .map(|s| s.parse::<Operation>().unwrap().to_string())
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_invalid_rotation() {
+        let expected = Err(Error::InvalidArgument(
+            String::from("rotate"),
+            String::from("45"),
+        ));
+        let result = "rotate=45".parse::<Operation>();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_invalid_sigma() {
+        let inputs = [
+            "blur=-1",
+            "blur=NaN",
+            "blur=inf",
+            "unsharp=0",
+            "unsharp=-2,3",
+        ];
+
+        let expected: Vec<_> = inputs
+            .iter()
+            .map(|input| {
+                let (name, arg) = input.split_once('=').unwrap();
+                Err(Error::InvalidArgument(name.to_string(), arg.to_string()))
+            })
+            .collect();
+        let result: Vec<_> = inputs.iter().map(|s| s.parse::<Operation>()).collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_missing_argument() {
+        let expected = Err(Error::MissingArgument(String::from("blur")));
+        let result = "blur".parse::<Operation>();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_unknown_operation() {
+        let expected = Err(Error::UnknownOperation(String::from("sepia")));
+        let result = "sepia=1".parse::<Operation>();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn resize_preserves_aspect_ratio() {
+        let img = DynamicImage::ImageLuma8(GrayImage::new(100, 50));
+
+        let expected = (40, 20);
+        let result = Operation::Resize(Some(40), None).apply(img).dimensions();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn identity_gamma() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(16, 16, |x, y| {
+            Luma([u8::try_from(x * 16 + y).unwrap()])
+        }));
+
+        let expected = img.to_luma8();
+        let result = Operation::Gamma(1.0).apply(img).to_luma8();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn equalize_stretches_range() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(2, 1, |x, _| {
+            Luma([if x == 0 { 100 } else { 150 }])
+        }));
+
+        let expected = vec![0, 255];
+        let result = Operation::Equalize.apply(img).to_luma8().into_raw();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
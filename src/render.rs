@@ -0,0 +1,857 @@
+use crate::edges::{self, EdgeDetector};
+use crate::error::{CellError, Error, ErrorPolicy, Result};
+use crate::glyphs::{GlyphSet, GlyphSource, Rasterization};
+use crate::image_utils::{self, partition_rects, AlphaMode};
+use crate::preprocess::{self, Operation};
+use crate::similarity::{self, Points};
+
+use image::{DynamicImage, GenericImageView, GrayImage, Pixel, Rgb, Rgba, SubImage};
+use rayon::prelude::*;
+use std::collections::VecDeque;
+
+/// Factor the error of the line glyph following a strongly oriented cell is scaled by
+const ORIENTATION_BIAS: f64 = 0.5;
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SimilarityMetric {
+    /// Hausdorff Distance
+    #[default]
+    Hausdorff,
+
+    /// Hamming Distance
+    Hamming,
+
+    /// Levenshtein Distance
+    Levenshtein,
+}
+
+fn ink_points<I>(img: &I, points: &mut Points) -> std::result::Result<(), CellError>
+where
+    I: GenericImageView,
+    I::Pixel: Pixel<Subpixel = u8>,
+{
+    points.clear();
+
+    img.pixels()
+        .filter(|(_, _, p)| image_utils::is_ink(p.to_rgba()))
+        .try_for_each(|(x, y, _)| {
+            let x = u16::try_from(x).map_err(|_| CellError::Coordinates)?;
+            let y = u16::try_from(y).map_err(|_| CellError::Coordinates)?;
+            points.insert((x, y));
+            Ok(())
+        })
+}
+
+/// Ranks every glyph against the cell's points, leaving the best `keep` of them in `ranked`
+/// in ascending order of error. The error of the `favored` glyph is scaled by
+/// [`ORIENTATION_BIAS`].
+fn match_char<F, E>(
+    img_points: &Points,
+    glyphs: &GlyphSet,
+    error_calc: F,
+    favored: Option<char>,
+    ranked: &mut Vec<Candidate>,
+    keep: usize,
+) -> std::result::Result<char, CellError>
+where
+    F: Fn(&Points, &Points) -> std::result::Result<f64, E>,
+    E: std::error::Error,
+{
+    let by_score =
+        |a: &Candidate, b: &Candidate| a.score.total_cmp(&b.score).then(a.glyph.cmp(&b.glyph));
+
+    let mut failure = None;
+
+    ranked.clear();
+    ranked.extend(glyphs.iter().filter_map(|(c, font_points)| {
+        match error_calc(img_points, font_points) {
+            Ok(score) if favored == Some(*c) => Some(Candidate {
+                glyph: *c,
+                score: score * ORIENTATION_BIAS,
+            }),
+            Ok(score) => Some(Candidate { glyph: *c, score }),
+            Err(e) => {
+                failure.get_or_insert_with(|| e.to_string());
+                None
+            }
+        }
+    }));
+
+    let keep = keep.clamp(1, ranked.len().max(1));
+    if ranked.len() > keep {
+        ranked.select_nth_unstable_by(keep - 1, by_score);
+        ranked.truncate(keep);
+    }
+    ranked.sort_unstable_by(by_score);
+
+    ranked.first().map(|c| c.glyph).ok_or_else(|| {
+        CellError::Unmatched(failure.unwrap_or_else(|| String::from("no candidate glyphs")))
+    })
+}
+
+/// Buffers reused between the cells matched on a thread
+#[derive(Default)]
+struct Scratch {
+    points: Points,
+    ranked: Vec<Candidate>,
+}
+
+/// Average color of the ink in a cell, or of the whole cell when it has none
+fn cell_color(s: &SubImage<&DynamicImage>, color: &DynamicImage) -> Rgb<u8> {
+    let (x_offset, y_offset) = s.offsets();
+    let color_at = |x: u32, y: u32| color.get_pixel(x + x_offset, y + y_offset);
+
+    let average = |pixels: &mut dyn Iterator<Item = Rgba<u8>>| {
+        let (sum, count) = pixels.fold(([0u64; 3], 0u64), |(mut sum, count), p| {
+            sum.iter_mut()
+                .zip(p.0)
+                .for_each(|(s, c)| *s += u64::from(c));
+            (sum, count + 1)
+        });
+
+        (count > 0).then(|| Rgb(sum.map(|s| u8::try_from(s / count).unwrap_or(u8::MAX))))
+    };
+
+    average(
+        &mut s
+            .pixels()
+            .filter(|(_, _, p)| image_utils::is_ink(*p))
+            .map(|(x, y, _)| color_at(x, y)),
+    )
+    .or_else(|| average(&mut s.pixels().map(|(x, y, _)| color_at(x, y))))
+    .unwrap_or(Rgb([255, 255, 255]))
+}
+
+/// Everything that determines how an image is turned into characters
+pub struct Settings {
+    /// Size of the square cells matched against outline fonts, bitmap fonts bring their own
+    pub pixels_per_char: u32,
+    pub rasterization: Rasterization,
+
+    /// Only these chars are candidates, every char of the fonts is when unset
+    pub charset: Option<String>,
+
+    pub similarity_metric: SimilarityMetric,
+    pub alpha: AlphaMode,
+    pub background: Rgb<u8>,
+    pub operations: Vec<Operation>,
+    pub edges: Option<EdgeDetector>,
+    pub edge_threshold: f32,
+    pub edge_orientation: bool,
+
+    /// Runners up kept for every cell
+    pub candidates: usize,
+
+    pub on_error: ErrorPolicy,
+
+    /// Character used for cells that can't be matched, unless failing
+    pub fallback_char: char,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            pixels_per_char: 50,
+            rasterization: Rasterization::default(),
+            charset: None,
+            similarity_metric: SimilarityMetric::default(),
+            alpha: AlphaMode::default(),
+            background: Rgb([255, 255, 255]),
+            operations: Vec::new(),
+            edges: None,
+            edge_threshold: 0.25,
+            edge_orientation: false,
+            candidates: 3,
+            on_error: ErrorPolicy::default(),
+            fallback_char: ' ',
+        }
+    }
+}
+
+/// Renders images with a font whose glyphs are rasterized once up front
+pub struct Renderer {
+    /// Fallback chain, the first font covering a char supplies its glyph
+    fonts: Vec<GlyphSource>,
+    glyphs: GlyphSet,
+    cell_size: (u32, u32),
+    settings: Settings,
+}
+
+impl Renderer {
+    /// The first font decides the size of the cells, a bitmap font's own or else square
+    /// cells of `pixels_per_char`
+    pub fn new(fonts: Vec<GlyphSource>, settings: Settings) -> Self {
+        let cell_size = fonts
+            .first()
+            .and_then(GlyphSource::cell_size)
+            .unwrap_or((settings.pixels_per_char, settings.pixels_per_char));
+        let glyphs = GlyphSet::new(
+            &fonts,
+            cell_size,
+            &settings.rasterization,
+            settings.charset.as_deref(),
+        );
+
+        Renderer {
+            fonts,
+            glyphs,
+            cell_size,
+            settings,
+        }
+    }
+
+    #[must_use]
+    pub fn fonts(&self) -> &[GlyphSource] {
+        &self.fonts
+    }
+
+    /// Width and height of every cell in pixels
+    #[must_use]
+    pub fn cell_size(&self) -> (u32, u32) {
+        self.cell_size
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Rasterized points of a candidate glyph
+    #[must_use]
+    pub fn glyph_points(&self, c: char) -> Option<&Points> {
+        self.glyphs.get(c)
+    }
+
+    /// Ink of the cell at `row` and `column` of a prepared image, as matched against glyphs
+    #[must_use]
+    pub fn cell_points(&self, prepared: &Prepared, row: u32, column: u32) -> Option<Points> {
+        let (cell_width, cell_height) = self.cell_size;
+        let (x, y) = (column * cell_width, row * cell_height);
+        let (width, height) = prepared.gray.dimensions();
+
+        if x + cell_width > width || y + cell_height > height {
+            return None;
+        }
+
+        let mut points = Points::new();
+        let cell = prepared.gray.view(x, y, cell_width, cell_height);
+        ink_points(&*cell, &mut points).ok()?;
+        Some(points)
+    }
+
+    /// Applies alpha handling, preprocessing and edge detection, producing the grayscale
+    /// image that gets partitioned alongside the color image cells take their colors from.
+    /// Takes any image buffer `image` converts into a [`DynamicImage`].
+    #[must_use]
+    pub fn prepare(&self, img: impl Into<DynamicImage>) -> Prepared {
+        let img = img.into();
+        let img = match self.settings.alpha {
+            AlphaMode::Background => img,
+            AlphaMode::Composite => image_utils::composite(&img, self.settings.background),
+        };
+        let color = preprocess::apply(img, &self.settings.operations);
+
+        let gray = match self.settings.edges {
+            Some(detector) => edges::detect(&color, detector, self.settings.edge_threshold),
+            None => color.grayscale(),
+        };
+
+        Prepared { color, gray }
+    }
+
+    /// Prepares an encoded image, its format sniffed from its bytes
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Decode`] if the bytes aren't an image in a supported format
+    pub fn prepare_bytes(&self, bytes: &[u8]) -> Result<Prepared> {
+        Ok(self.prepare(image::load_from_memory(bytes)?))
+    }
+
+    /// Prepares raw row major 8 bit grayscale pixels
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Image`] unless there's exactly one byte per pixel
+    pub fn prepare_luma(&self, width: u32, height: u32, pixels: Vec<u8>) -> Result<Prepared> {
+        Ok(self.prepare(image_utils::luma_image(width, height, pixels)?))
+    }
+
+    fn closest_char<I>(&self, s: &I, scratch: &mut Scratch) -> std::result::Result<char, CellError>
+    where
+        I: GenericImageView,
+        I::Pixel: Pixel<Subpixel = u8>,
+    {
+        scratch.ranked.clear();
+        ink_points(s, &mut scratch.points)?;
+
+        // nothing to compare, and no glyph is closer to nothing than a space
+        if scratch.points.is_empty() {
+            return Ok(' ');
+        }
+
+        // the line glyph following the gradient is favored when it's a candidate
+        let favored = self
+            .settings
+            .edge_orientation
+            .then(|| edges::dominant_orientation(s))
+            .flatten()
+            .map(edges::Orientation::glyph)
+            .filter(|c| self.glyphs.get(*c).is_some());
+
+        let (points, ranked) = (&scratch.points, &mut scratch.ranked);
+        let keep = self.settings.candidates + 1;
+
+        #[allow(clippy::cast_precision_loss)]
+        match self.settings.similarity_metric {
+            SimilarityMetric::Hausdorff => match_char(
+                points,
+                &self.glyphs,
+                |p1, p2| similarity::hausdorff_distance(p1, p2).map(f64::from),
+                favored,
+                ranked,
+                keep,
+            ),
+            SimilarityMetric::Hamming => match_char(
+                points,
+                &self.glyphs,
+                |p1, p2| similarity::hamming_distance(p1, p2).map(|d| d as f64),
+                favored,
+                ranked,
+                keep,
+            ),
+            SimilarityMetric::Levenshtein => match_char(
+                points,
+                &self.glyphs,
+                |p1, p2| {
+                    Ok::<f64, std::convert::Infallible>(
+                        similarity::levenshtein_distance(p1, p2) as f64
+                    )
+                },
+                favored,
+                ranked,
+                keep,
+            ),
+        }
+    }
+
+    /// Applies the error policy to the outcome of matching the `i`th cell of a grid
+    fn resolve(
+        &self,
+        i: usize,
+        columns: usize,
+        matched: std::result::Result<char, CellError>,
+    ) -> Result<(char, Option<CellError>)> {
+        match matched {
+            Ok(glyph) => Ok((glyph, None)),
+            Err(error) if self.settings.on_error == ErrorPolicy::Fail => Err(Error::Cell {
+                row: i / columns,
+                column: i % columns,
+                error,
+            }),
+            Err(error) => Ok((self.settings.fallback_char, Some(error))),
+        }
+    }
+
+    /// Matches every cell of an already prepared image
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Cell`] for the first cell that can't be matched under [`ErrorPolicy::Fail`]
+    pub fn render_prepared(&self, prepared: &Prepared) -> Result<Grid> {
+        let (rects, columns) = self.partition(prepared.gray.dimensions());
+        let span = tracing::Span::current();
+
+        let cells = rects
+            .par_iter()
+            .enumerate()
+            .map_init(Scratch::default, |scratch, (i, rect)| {
+                self.match_cell(prepared, (i, columns), *rect, scratch, &span)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Grid { columns, cells })
+    }
+
+    /// Rows of cells of a prepared image, matched in parallel a few rows at a time and
+    /// yielded in order, so the first rows are available long before the last ones and only
+    /// a batch of rows is held at once
+    #[must_use]
+    pub fn rows<'a>(&'a self, prepared: &'a Prepared) -> Rows<'a> {
+        let (rects, columns) = self.partition(prepared.gray.dimensions());
+
+        Rows {
+            renderer: self,
+            prepared,
+            rects,
+            columns,
+            batch: rayon::current_num_threads(),
+            next_row: 0,
+            matched: VecDeque::new(),
+            span: tracing::Span::current(),
+            failed: false,
+        }
+    }
+
+    /// Matches the `i`th cell of a grid `columns` wide, covering `rect` of the image
+    fn match_cell(
+        &self,
+        prepared: &Prepared,
+        (i, columns): (usize, usize),
+        (x, y, width, height): (u32, u32, u32, u32),
+        scratch: &mut Scratch,
+        span: &tracing::Span,
+    ) -> Result<Cell> {
+        let s = prepared.gray.view(x, y, width, height);
+        let (glyph, error) = self.resolve(i, columns, self.closest_char(&*s, scratch))?;
+        let mut ranked = scratch.ranked.iter().cloned();
+
+        let cell = Cell {
+            glyph,
+            color: cell_color(&s, &prepared.color),
+            score: ranked.next().map(|c| c.score).filter(|_| error.is_none()),
+            candidates: ranked.collect(),
+            error,
+        };
+
+        tracing::debug!(
+            parent: span,
+            row = i / columns,
+            column = i % columns,
+            glyph = %cell.glyph,
+            score = cell.score,
+            error = cell.error.as_ref().map(tracing::field::display),
+            "matched cell"
+        );
+
+        Ok(cell)
+    }
+
+    /// Partitions of an image into cells, and the number of columns they form
+    fn partition(&self, dimensions: (u32, u32)) -> (Vec<(u32, u32, u32, u32)>, usize) {
+        let (cell_width, cell_height) = self.cell_size;
+        let rects = partition_rects(dimensions, cell_width, cell_height, false);
+        let columns = rects.iter().take_while(|(_, y, _, _)| *y == 0).count();
+
+        (rects, columns.max(1))
+    }
+
+    #[must_use]
+    pub fn frame_buffers(&self, dimensions: (u32, u32)) -> FrameBuffers {
+        let (rects, columns) = self.partition(dimensions);
+
+        FrameBuffers {
+            dimensions,
+            cells: Vec::with_capacity(rects.len()),
+            errors: Vec::with_capacity(rects.len()),
+            rects,
+            columns,
+            scratch: std::iter::repeat_with(Scratch::default)
+                .take(rayon::current_num_threads())
+                .collect(),
+            preprocessed: GrayImage::new(0, 0),
+            edges: GrayImage::new(0, 0),
+            gradients: Vec::new(),
+        }
+    }
+
+    /// Preprocessing and edge detection of a grayscale frame into the reused images of
+    /// `buffers`, returning the image to match
+    fn prepare_frame<'a>(
+        &self,
+        frame: &'a GrayImage,
+        preprocessed: &'a mut GrayImage,
+        edges: &'a mut GrayImage,
+        gradients: &mut Vec<(f32, f32)>,
+    ) -> &'a GrayImage {
+        let mut img = frame;
+
+        if !self.settings.operations.is_empty() {
+            if preprocessed.dimensions() == frame.dimensions() {
+                preprocessed.copy_from_slice(frame);
+            } else {
+                preprocessed.clone_from(frame);
+            }
+            preprocess::apply_gray(preprocessed, &self.settings.operations);
+            img = preprocessed;
+        }
+
+        if let Some(detector) = self.settings.edges {
+            edges::detect_into(
+                img,
+                detector,
+                self.settings.edge_threshold,
+                gradients,
+                edges,
+            );
+            img = edges;
+        }
+
+        img
+    }
+
+    /// Prepares and matches every cell of a grayscale frame into reused buffers. Cells are
+    /// matched in a chunk per thread, each with its own scratch buffers, so equally sized
+    /// frames only allocate for preprocessing that changes the frame's geometry or
+    /// sharpness and for Canny edges.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Cell`] for the first cell that can't be matched under [`ErrorPolicy::Fail`]
+    pub fn render_frame(&self, frame: &GrayImage, buffers: &mut FrameBuffers) -> Result<()> {
+        let FrameBuffers {
+            dimensions,
+            rects,
+            columns,
+            cells,
+            errors,
+            scratch,
+            preprocessed,
+            edges,
+            gradients,
+        } = buffers;
+
+        let img = self.prepare_frame(frame, preprocessed, edges, gradients);
+        // preprocessing may resize, crop or rotate frames
+        if img.dimensions() != *dimensions {
+            *dimensions = img.dimensions();
+            (*rects, *columns) = self.partition(*dimensions);
+        }
+
+        cells.resize(rects.len(), ' ');
+        errors.resize(rects.len(), None);
+        let chunk = rects.len().div_ceil(scratch.len().max(1)).max(1);
+
+        rects
+            .par_chunks(chunk)
+            .zip(cells.par_chunks_mut(chunk))
+            .zip(errors.par_chunks_mut(chunk))
+            .zip(scratch.par_iter_mut())
+            .enumerate()
+            .try_for_each(|(n, (((rects, cells), errors), scratch))| {
+                let cells = rects.iter().zip(cells).zip(errors).enumerate();
+                for (i, ((&(x, y, width, height), cell), error)) in cells {
+                    let matched = self.closest_char(&*img.view(x, y, width, height), scratch);
+                    (*cell, *error) = self.resolve(n * chunk + i, *columns, matched)?;
+                }
+                Ok(())
+            })
+    }
+}
+
+/// An image after alpha handling, preprocessing and edge detection
+pub struct Prepared {
+    pub color: DynamicImage,
+    pub gray: DynamicImage,
+}
+
+/// A glyph and its error against a cell
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Candidate {
+    #[serde(rename = "char")]
+    pub glyph: char,
+    pub score: f64,
+}
+
+/// A matched character and the color of the image beneath it
+#[derive(Clone, Debug)]
+pub struct Cell {
+    pub glyph: char,
+    pub color: Rgb<u8>,
+
+    /// Error of the matched glyph, `None` when it wasn't chosen by matching. Favored line
+    /// glyphs are scored with the orientation bias applied.
+    pub score: Option<f64>,
+
+    /// Closest glyphs after the matched one, best first
+    pub candidates: Vec<Candidate>,
+
+    /// Why the cell holds the fallback character instead of a match
+    pub error: Option<CellError>,
+}
+
+/// Matched cells in row major order
+pub struct Grid {
+    columns: usize,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    /// Grid of `cells` in row major order, `columns` wide
+    #[must_use]
+    pub fn new(columns: usize, cells: Vec<Cell>) -> Self {
+        Grid {
+            columns: columns.max(1),
+            cells,
+        }
+    }
+
+    /// Columns and rows
+    #[must_use]
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.columns, self.cells.len().div_ceil(self.columns))
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, Cell> {
+        self.cells.chunks(self.columns)
+    }
+
+    /// Why each cell that fell back couldn't be matched
+    pub fn fallbacks(&self) -> impl Iterator<Item = &CellError> {
+        self.cells.iter().filter_map(|cell| cell.error.as_ref())
+    }
+
+    #[must_use]
+    pub fn text_rows(&self) -> Vec<String> {
+        self.rows()
+            .map(|row| row.iter().map(|cell| cell.glyph).collect())
+            .collect()
+    }
+}
+
+/// Rows of cells yielded in order as they are matched, see [`Renderer::rows`]. Iteration
+/// stops after the first error.
+pub struct Rows<'a> {
+    renderer: &'a Renderer,
+    prepared: &'a Prepared,
+    rects: Vec<(u32, u32, u32, u32)>,
+    columns: usize,
+
+    /// Rows matched together, in parallel
+    batch: usize,
+    next_row: usize,
+    matched: VecDeque<Vec<Cell>>,
+    span: tracing::Span,
+    failed: bool,
+}
+
+impl Rows<'_> {
+    /// Matches `rows` rows at a time instead of one per thread
+    #[must_use]
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch = rows.max(1);
+        self
+    }
+
+    /// Columns and rows of the whole grid
+    #[must_use]
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.columns, self.rects.len() / self.columns)
+    }
+
+    fn match_batch(&mut self) -> Result<()> {
+        let (columns, rows) = self.dimensions();
+        let end = (self.next_row + self.batch).min(rows);
+        let first = self.next_row * columns;
+        let (renderer, prepared, span) = (self.renderer, self.prepared, &self.span);
+
+        let cells: Vec<Cell> = self.rects[first..end * columns]
+            .par_iter()
+            .enumerate()
+            .map_init(Scratch::default, |scratch, (i, rect)| {
+                renderer.match_cell(prepared, (first + i, columns), *rect, scratch, span)
+            })
+            .collect::<Result<_>>()?;
+
+        let mut cells = cells.into_iter();
+        for _ in self.next_row..end {
+            self.matched
+                .push_back(cells.by_ref().take(columns).collect());
+        }
+        self.next_row = end;
+
+        Ok(())
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = Result<Vec<Cell>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        if self.matched.is_empty() && self.next_row < self.dimensions().1 {
+            if let Err(e) = self.match_batch() {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+
+        self.matched.pop_front().map(Ok)
+    }
+}
+
+/// Partitions, matched cells, scratch buffers and prepared images reused across equally
+/// sized frames
+pub struct FrameBuffers {
+    /// Dimensions of the matched image, after preprocessing
+    dimensions: (u32, u32),
+    rects: Vec<(u32, u32, u32, u32)>,
+    columns: usize,
+    cells: Vec<char>,
+    errors: Vec<Option<CellError>>,
+
+    /// Scratch buffers of each chunk of cells matched in parallel
+    scratch: Vec<Scratch>,
+    preprocessed: GrayImage,
+    edges: GrayImage,
+    gradients: Vec<(f32, f32)>,
+}
+
+impl FrameBuffers {
+    /// Why each cell of the last frame that fell back couldn't be matched
+    pub fn fallbacks(&self) -> impl Iterator<Item = &CellError> {
+        self.errors.iter().flatten()
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, char> {
+        self.cells.chunks(self.columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap_font::BitmapFont;
+
+    use image::GrayImage;
+
+    /// Renderer of a 2x2 bitmap font with a full `#`, a left column `|` and a bottom row `_`
+    fn block_renderer(settings: Settings) -> Renderer {
+        let bdf = "STARTFONT 2.1\nFONTBOUNDINGBOX 2 2 0 0\n\
+            STARTCHAR full\nENCODING 35\nBBX 2 2 0 0\nBITMAP\nC0\nC0\nENDCHAR\n\
+            STARTCHAR left\nENCODING 124\nBBX 1 2 0 0\nBITMAP\n80\n80\nENDCHAR\n\
+            STARTCHAR bottom\nENCODING 95\nBBX 2 1 0 0\nBITMAP\nC0\nENDCHAR\n\
+            ENDFONT\n";
+        let font = BitmapFont::parse(bdf.as_bytes()).unwrap();
+        let settings = Settings {
+            similarity_metric: SimilarityMetric::Hamming,
+            ..settings
+        };
+
+        Renderer::new(vec![GlyphSource::Bitmap(font)], settings)
+    }
+
+    /// 3 columns by 4 rows of 2x2 cells, each picking a glyph of [`block_renderer`] by its
+    /// position
+    fn block_image() -> GrayImage {
+        GrayImage::from_fn(6, 8, |x, y| {
+            let ink = match (x / 2 + y / 2) % 3 {
+                0 => true,
+                1 => x % 2 == 0,
+                _ => y % 2 == 1,
+            };
+            image::Luma([if ink { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn rows_stream_the_grid_in_order() {
+        let renderer = block_renderer(Settings::default());
+        let img = block_image();
+        let prepared = renderer.prepare(DynamicImage::ImageLuma8(img));
+
+        let expected = renderer.render_prepared(&prepared).unwrap().text_rows();
+        let result: Vec<String> = renderer
+            .rows(&prepared)
+            .batch_size(3)
+            .map(|row| row.unwrap().iter().map(|cell| cell.glyph).collect())
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+        assert_eq!(vec!["#|_", "|_#", "_#|", "#|_"], result);
+    }
+
+    #[test]
+    fn encoded_images_render_like_decoded_ones() {
+        let renderer = block_renderer(Settings::default());
+        let img = DynamicImage::ImageLuma8(block_image());
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let prepared = renderer.prepare(img);
+        let expected = (
+            prepared.gray.dimensions(),
+            renderer.render_prepared(&prepared).unwrap().text_rows(),
+        );
+        let prepared = renderer.prepare_bytes(&png).unwrap();
+        let result = (
+            prepared.gray.dimensions(),
+            renderer.render_prepared(&prepared).unwrap().text_rows(),
+        );
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn frames_render_like_prepared_images() {
+        let renderer = block_renderer(Settings {
+            operations: vec![Operation::Resize(Some(12), None), Operation::Contrast(20.0)],
+            ..Settings::default()
+        });
+        let frame = block_image();
+        let mut buffers = renderer.frame_buffers(frame.dimensions());
+
+        let prepared = renderer.prepare(DynamicImage::ImageLuma8(frame.clone()));
+        let expected = renderer.render_prepared(&prepared).unwrap().text_rows();
+        let expected = vec![expected.clone(), expected];
+        let result: Vec<Vec<String>> = (0..2)
+            .map(|_| {
+                renderer.render_frame(&frame, &mut buffers).unwrap();
+                buffers.rows().map(|row| row.iter().collect()).collect()
+            })
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn orientation_biases_the_match() {
+        let bdf = "STARTFONT 2.1\nFONTBOUNDINGBOX 4 4 0 0\n\
+            STARTCHAR bar\nENCODING 65372\nBBX 4 4 0 0\nBITMAP\n60\n40\n40\n40\nENDCHAR\n\
+            STARTCHAR dash\nENCODING 65293\nBBX 4 4 0 0\nBITMAP\n00\nF0\n00\n00\nENDCHAR\n\
+            STARTCHAR n\nENCODING 110\nBBX 4 4 0 0\nBITMAP\n60\n60\n60\n00\nENDCHAR\n\
+            ENDFONT\n";
+        // a vertical bar two pixels wide, closer to `n` than to `｜`
+        let img = GrayImage::from_fn(4, 4, |x, _| {
+            image::Luma([if x == 1 || x == 2 { 0 } else { 255 }])
+        });
+
+        let expected = vec![('n', Some(2.0)), ('｜', Some(1.5))];
+        let result: Vec<_> = [false, true]
+            .into_iter()
+            .map(|edge_orientation| {
+                let settings = Settings {
+                    similarity_metric: SimilarityMetric::Levenshtein,
+                    charset: Some(String::from("n｜－")),
+                    edge_orientation,
+                    ..Settings::default()
+                };
+                let font = BitmapFont::parse(bdf.as_bytes()).unwrap();
+                let renderer = Renderer::new(vec![GlyphSource::Bitmap(font)], settings);
+                let prepared = renderer.prepare(DynamicImage::ImageLuma8(img.clone()));
+                let grid = renderer.render_prepared(&prepared).unwrap();
+                let cell = &grid.rows().next().unwrap()[0];
+                (cell.glyph, cell.score)
+            })
+            .collect();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -10,24 +10,23 @@ pub enum Error {
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::Cardinality(i, j) => write!(
+            // the same for every pair of sizes, so that cells failing for this reason are
+            // counted together
+            Error::Cardinality(_, _) => write!(
                 f,
-                "Cannot compute Hamming distance between sets with cardinality {i} and {j}"
+                "Hamming distance isn't well defined for sets with different cardinalities"
             ),
         }
     }
 }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        match self {
-            Error::Cardinality(_, _) => {
-                "Hamming distance isn't well defined for sets with different cardinalities"
-            }
-        }
-    }
-}
+impl std::error::Error for Error {}
 
+/// Points in only one of two equally sized sets
+///
+/// # Errors
+///
+/// [`Error::Cardinality`] when the sets differ in size
 #[allow(clippy::module_name_repetitions)]
 pub fn hamming_distance(a: &Points, b: &Points) -> Result<usize> {
     if a.len() != b.len() {
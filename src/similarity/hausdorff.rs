@@ -17,20 +17,18 @@ impl std::fmt::Display for Error {
     }
 }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        match self {
-            Error::EmptySet => "Hausdorff distance isn't well defined for empty sets",
-            Error::NoMinimum => "Unable to find minimum",
-        }
-    }
-}
+impl std::error::Error for Error {}
 
 fn euclidean_distance(x: (u16, u16), y: (u16, u16)) -> f64 {
     f64::from((i32::from(x.0) - i32::from(y.0)).pow(2) + (i32::from(x.1) - i32::from(y.1)).pow(2))
         .sqrt()
 }
 
+/// Greatest distance from a point of either set to the closest point of the other
+///
+/// # Errors
+///
+/// [`Error::EmptySet`] when either set is empty
 #[allow(clippy::module_name_repetitions)]
 pub fn hausdorff_distance(a: &Points, b: &Points) -> Result<f32> {
     if a.is_empty() || b.is_empty() {
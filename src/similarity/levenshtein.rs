@@ -2,6 +2,7 @@ use super::Points;
 use core::cmp::max;
 
 #[allow(clippy::module_name_repetitions)]
+#[must_use]
 pub fn levenshtein_distance(a: &Points, b: &Points) -> usize {
     let intersection_cardinality = a.intersection(b).count();
 
@@ -0,0 +1,319 @@
+use derm_rs::font_utils::xdg_config_home;
+
+use std::path::{Path, PathBuf};
+
+/// Terminal emulators whose configured font can be read
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Terminal {
+    Alacritty,
+    Kitty,
+    WezTerm,
+    Foot,
+    Gnome,
+}
+
+impl Terminal {
+    /// The terminal derm is running in, from the environment it sets
+    fn running() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+
+        if var("TERM_PROGRAM") == "WezTerm" || !var("WEZTERM_EXECUTABLE").is_empty() {
+            Some(Terminal::WezTerm)
+        } else if term == "xterm-kitty" || !var("KITTY_WINDOW_ID").is_empty() {
+            Some(Terminal::Kitty)
+        } else if term == "alacritty" || !var("ALACRITTY_WINDOW_ID").is_empty() {
+            Some(Terminal::Alacritty)
+        } else if term.starts_with("foot") {
+            Some(Terminal::Foot)
+        } else if !var("GNOME_TERMINAL_SCREEN").is_empty()
+            || !var("GNOME_TERMINAL_SERVICE").is_empty()
+        {
+            Some(Terminal::Gnome)
+        } else {
+            None
+        }
+    }
+
+    fn config_files(self, home: Option<&Path>) -> Vec<PathBuf> {
+        let config = xdg_config_home(home);
+        let env_path = |name: &str| std::env::var_os(name).map(PathBuf::from);
+
+        match self {
+            Terminal::Alacritty => {
+                let mut files = vec![
+                    config.join("alacritty/alacritty.toml"),
+                    config.join("alacritty/alacritty.yml"),
+                ];
+                files.extend(home.map(|h| h.join(".alacritty.toml")));
+                files.extend(home.map(|h| h.join(".alacritty.yml")));
+                files
+            }
+            Terminal::Kitty => vec![env_path("KITTY_CONFIG_DIRECTORY")
+                .unwrap_or_else(|| config.join("kitty"))
+                .join("kitty.conf")],
+            Terminal::WezTerm => {
+                let mut files: Vec<_> = env_path("WEZTERM_CONFIG_FILE").into_iter().collect();
+                files.push(config.join("wezterm/wezterm.lua"));
+                files.extend(home.map(|h| h.join(".wezterm.lua")));
+                files
+            }
+            Terminal::Foot => vec![config.join("foot/foot.ini")],
+            Terminal::Gnome => Vec::new(),
+        }
+    }
+
+    fn parse(self, config: &str) -> Option<String> {
+        match self {
+            Terminal::Alacritty => alacritty_font(config),
+            Terminal::Kitty => kitty_font(config),
+            Terminal::WezTerm => wezterm_font(config),
+            Terminal::Foot => foot_font(config),
+            Terminal::Gnome => gnome_terminal_font(config),
+        }
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// `[font.normal] family` of `alacritty.toml`, or `font: normal: family:` of the older YAML
+/// configuration
+pub fn alacritty_font(config: &str) -> Option<String> {
+    if let Ok(table) = config.parse::<toml::Table>() {
+        return table
+            .get("font")?
+            .get("normal")?
+            .get("family")?
+            .as_str()
+            .map(String::from);
+    }
+
+    let mut section = Vec::new();
+    for line in config.lines() {
+        let content = line.split('#').next().unwrap_or_default();
+        let Some((key, value)) = content.split_once(':') else {
+            continue;
+        };
+        let depth = content.len() - content.trim_start().len();
+
+        section.retain(|(d, _)| *d < depth);
+        section.push((depth, key.trim()));
+
+        let path: Vec<&str> = section.iter().map(|(_, key)| *key).collect();
+        if path == ["font", "normal", "family"] && !value.trim().is_empty() {
+            return Some(unquote(value).to_string());
+        }
+    }
+
+    None
+}
+
+/// `font_family` of `kitty.conf`, either a plain name or `family="..."`
+pub fn kitty_font(config: &str) -> Option<String> {
+    config
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("font_family"))
+        .filter(|value| value.starts_with(char::is_whitespace))
+        .map(|value| {
+            let value = value.trim();
+            match value.split_once("family=") {
+                Some((_, family)) => {
+                    let family = family.trim_start();
+                    match family.strip_prefix('"') {
+                        Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                        None => family.split_whitespace().next().unwrap_or_default(),
+                    }
+                }
+                None => value,
+            }
+        })
+        .next_back()
+        .filter(|family| !family.is_empty() && *family != "auto")
+        .map(String::from)
+}
+
+/// First family of `wezterm.font(...)` or `wezterm.font_with_fallback(...)` in `wezterm.lua`
+pub fn wezterm_font(config: &str) -> Option<String> {
+    let code: String = config
+        .lines()
+        .map(|line| line.split("--").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let (_, call) = code.split_once("wezterm.font")?;
+    let start = call.find(['"', '\''])?;
+    let quote = call[start..].chars().next()?;
+    let (family, _) = call[start + 1..].split_once(quote)?;
+
+    Some(family.to_string())
+}
+
+/// First font of `font=` in `foot.ini`, without its size and other attributes
+pub fn foot_font(config: &str) -> Option<String> {
+    let mut in_main = true;
+
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_main = line == "[main]";
+        } else if let Some((key, value)) = line.split_once('=') {
+            if in_main && key.trim() == "font" {
+                let first = value.split(',').next().unwrap_or_default();
+                let family = first.split(':').next().unwrap_or_default().trim();
+                return Some(family.to_string()).filter(|f| !f.is_empty());
+            }
+        }
+    }
+
+    None
+}
+
+fn value<'a>(entries: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    entries.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// Font of the default GNOME Terminal profile in `dconf dump /org/gnome/terminal/` output,
+/// unless the profile uses the system font. The trailing size is dropped.
+pub fn gnome_terminal_font(dump: &str) -> Option<String> {
+    let mut sections: Vec<(&str, Vec<(&str, &str)>)> = Vec::new();
+    for line in dump.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name, Vec::new()));
+        } else if let (Some((_, entries)), Some((key, value))) =
+            (sections.last_mut(), line.split_once('='))
+        {
+            entries.push((key.trim(), unquote(value)));
+        }
+    }
+
+    let default = sections
+        .iter()
+        .find_map(|(name, entries)| {
+            name.ends_with("profiles:")
+                .then(|| value(entries, "default"))
+                .flatten()
+        })
+        .unwrap_or_default();
+
+    let (_, profile) = sections
+        .iter()
+        .filter(|(name, _)| name.contains("profiles:/:"))
+        .find(|(name, _)| default.is_empty() || name.ends_with(&format!(":{default}")))?;
+
+    // dconf leaves out keys at their default, which is the system font
+    if value(profile, "use-system-font") != Some("false") {
+        return None;
+    }
+
+    let font = value(profile, "font")?;
+    let family = match font.rsplit_once(' ') {
+        Some((family, size)) if size.parse::<f32>().is_ok() => family,
+        _ => font,
+    };
+
+    Some(family.to_string())
+}
+
+/// The font configured for the terminal derm is running in, if it can be found
+pub fn detect() -> Option<String> {
+    let terminal = Terminal::running()?;
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    let font = if terminal == Terminal::Gnome {
+        let dump = std::process::Command::new("dconf")
+            .args(["dump", "/org/gnome/terminal/"])
+            .output()
+            .ok()?;
+        terminal.parse(&String::from_utf8_lossy(&dump.stdout))
+    } else {
+        terminal
+            .config_files(home.as_deref())
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .find_map(|config| terminal.parse(&config))
+    };
+
+    tracing::debug!(?terminal, font, "terminal font");
+    font
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alacritty_toml_and_yaml() {
+        let toml = "[font]\nsize = 11\n\n[font.normal]\nfamily = \"Hack\"\nstyle = \"Regular\"\n";
+        let yaml = "font:\n  size: 11\n  normal:\n    family: Iosevka Term  # comment\n";
+
+        let expected = vec![
+            Some(String::from("Hack")),
+            Some(String::from("Iosevka Term")),
+        ];
+        let result = vec![alacritty_font(toml), alacritty_font(yaml)];
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn kitty_wezterm_and_foot() {
+        let expected = vec![
+            Some(String::from("Fira Code")),
+            Some(String::from("JetBrains Mono")),
+            Some(String::from("Hack")),
+            Some(String::from("Source Code Pro")),
+        ];
+        let result = vec![
+            kitty_font("font_size 11\nfont_family      Fira Code\nbold_font auto\n"),
+            kitty_font("font_family family=\"JetBrains Mono\" style=Regular\n"),
+            wezterm_font("-- wezterm.font(\"x\")\nconfig.font = wezterm.font_with_fallback { 'Hack', 'Noto' }\n"),
+            foot_font("[main]\nfont=Source Code Pro:size=11,Noto Color Emoji\n[colors]\n"),
+        ];
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn gnome_terminal_default_profile() {
+        let dump = "[legacy/profiles:]\n\
+            default='b1dcc9dd'\n\
+            list=['a', 'b1dcc9dd']\n\n\
+            [legacy/profiles:/:a]\n\
+            font='Monospace 12'\n\
+            use-system-font=false\n\n\
+            [legacy/profiles:/:b1dcc9dd]\n\
+            font='Ubuntu Mono Bold 13'\n\
+            use-system-font=false\n";
+
+        let expected = Some(String::from("Ubuntu Mono Bold"));
+        let result = gnome_terminal_font(dump);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+
+    #[test]
+    fn gnome_terminal_system_font_by_default() {
+        let dump = "[legacy/profiles:/:b1dcc9dd]\n\
+            font='Ubuntu Mono Bold 13'\n\
+            visible-name='Default'\n";
+
+        let expected = None;
+        let result = gnome_terminal_font(dump);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
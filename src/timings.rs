@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+/// Time spent in each pipeline stage, in the order stages were first recorded
+#[derive(Default)]
+pub struct Timings {
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    /// Adds to the time spent in a stage
+    pub fn record(&mut self, stage: &'static str, duration: Duration) {
+        match self.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, total)) => *total += duration,
+            None => self.stages.push((stage, duration)),
+        }
+    }
+
+    /// Adds the time spent in every stage of `other`
+    pub fn merge(&mut self, other: Timings) {
+        for (stage, duration) in other.stages {
+            self.record(stage, duration);
+        }
+    }
+
+    /// Runs `f` in a span for `stage`, recording how long it took
+    pub fn time<T>(&mut self, stage: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = tracing::info_span!("stage", stage).in_scope(f);
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    /// A line per stage with its time and share of the total
+    pub fn report(&self) -> Vec<String> {
+        let total: Duration = self.stages.iter().map(|(_, d)| *d).sum();
+        let width = self
+            .stages
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+
+        self.stages
+            .iter()
+            .map(|(name, duration)| {
+                let share = if total.is_zero() {
+                    0.0
+                } else {
+                    100.0 * duration.as_secs_f64() / total.as_secs_f64()
+                };
+
+                format!(
+                    "{name:<width$}  {:>10.3}ms  {share:>5.1}%",
+                    duration.as_secs_f64() * 1000.0
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_stages_accumulate() {
+        let mut timings = Timings::default();
+        timings.record("match", Duration::from_millis(3));
+        timings.record("decode", Duration::from_millis(2));
+        timings.record("match", Duration::from_millis(5));
+
+        let expected = vec![
+            "match        8.000ms   80.0%",
+            "decode       2.000ms   20.0%",
+        ];
+        let result = timings.report();
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}
@@ -13,13 +13,7 @@ impl std::fmt::Display for Error {
     }
 }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        match self {
-            Error::RowSize(_) => "Invalid Row Size",
-        }
-    }
-}
+impl std::error::Error for Error {}
 
 // TODO: change this
 pub trait Pointify {
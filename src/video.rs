@@ -0,0 +1,60 @@
+use crate::layout::Layout;
+use derm_rs::error::{Fallbacks, Result};
+use derm_rs::render::Renderer;
+
+use image::GrayImage;
+use std::io::{self, Read, Write};
+
+/// Renders raw 8 bit grayscale frames, such as `ffmpeg -f rawvideo -pix_fmt gray` output,
+/// until the input ends. The frame, prepared image, cell and row buffers are allocated once
+/// and reused.
+pub fn stream(
+    renderer: &Renderer,
+    layout: &Layout,
+    (width, height): (u32, u32),
+    mut input: impl Read,
+    mut output: impl Write,
+    in_place: bool,
+) -> Result<()> {
+    let mut frame = GrayImage::new(width, height);
+    let mut buffers = renderer.frame_buffers((width, height));
+    let mut rows: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut fallbacks = Fallbacks::default();
+
+    if in_place {
+        write!(output, "\x1b[2J")?;
+    }
+
+    for index in 0.. {
+        match input.read_exact(&mut frame) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+
+        renderer.render_frame(&frame, &mut buffers)?;
+        fallbacks.add(buffers.fallbacks());
+
+        text.clear();
+        if in_place {
+            text.push_str("\x1b[H");
+        } else if index > 0 {
+            text.push('\n');
+        }
+        rows.resize_with(buffers.rows().len(), String::new);
+        for (text_row, row) in rows.iter_mut().zip(buffers.rows()) {
+            text_row.clear();
+            text_row.extend(row);
+        }
+        layout.write(&rows, &mut text);
+
+        output.write_all(text.as_bytes())?;
+        output.flush()?;
+    }
+
+    let settings = renderer.settings();
+    fallbacks.report(settings.on_error, settings.fallback_char);
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use crate::layout::{boxed, UNICODE_BOX};
+use derm_rs::similarity::Points;
 use itertools::Itertools;
 
 pub fn print_to_console<T, I>(iterator: &I, row_size: usize, include: fn(T) -> bool)
@@ -20,17 +22,90 @@ where
             })
             .join("")
         })
-        .map(|row| "┃".to_owned() + &row.clone() + "┃")
         .collect_vec();
 
-    let top_border = vec!["┏".to_string() + &"━".repeat(row_size * h_per_v) + "┓"];
-    let bot_border = vec!["┗".to_string() + &"━".repeat(row_size * h_per_v) + "┛"];
-    let full_image = [top_border, formatted_rows, bot_border].concat();
+    let full_image = boxed(&formatted_rows, &UNICODE_BOX, 0);
 
-    print!(
+    eprint!(
         "{}",
         full_image
             .iter()
             .fold(String::new(), |s, row| s + row + "\n")
     );
 }
+
+/// Boxed rows of a `width` by `height` pixel area, each pixel two columns wide
+fn panel(width: u16, height: u16, shade: impl Fn(u16, u16) -> char) -> Vec<String> {
+    let rows: Vec<String> = (0..height)
+        .map(|y| (0..width).flat_map(|x| [shade(x, y); 2]).collect())
+        .collect();
+
+    boxed(&rows, &UNICODE_BOX, 0)
+}
+
+/// Equally tall panels next to each other, separated by a space
+fn side_by_side(panels: &[Vec<String>]) -> Vec<String> {
+    let height = panels.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..height)
+        .map(|y| {
+            panels
+                .iter()
+                .map(|panel| panel.get(y).map_or("", String::as_str))
+                .join(" ")
+        })
+        .collect()
+}
+
+/// A cell's ink, the glyph it matched and their overlap side by side. The overlap shows
+/// pixels in both as `█`, only in the cell as `░` and only in the glyph as `▒`.
+pub fn compare_cell(size: (u32, u32), partition: &Points, glyph: &Points) -> Vec<String> {
+    let extent = |axis: fn(&(u16, u16)) -> u16, size: u32| {
+        partition
+            .iter()
+            .chain(glyph)
+            .map(|p| axis(p) + 1)
+            .max()
+            .unwrap_or(0)
+            .max(u16::try_from(size).unwrap_or(u16::MAX))
+    };
+    let (width, height) = (extent(|p| p.0, size.0), extent(|p| p.1, size.1));
+
+    let ink = |points: &Points, x, y| if points.contains(&(x, y)) { '█' } else { ' ' };
+    let overlap = |x, y| match (partition.contains(&(x, y)), glyph.contains(&(x, y))) {
+        (true, true) => '█',
+        (true, false) => '░',
+        (false, true) => '▒',
+        (false, false) => ' ',
+    };
+
+    side_by_side(&[
+        panel(width, height, |x, y| ink(partition, x, y)),
+        panel(width, height, |x, y| ink(glyph, x, y)),
+        panel(width, height, overlap),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlap_panels() {
+        let partition = Points::from([(0, 0), (1, 0)]);
+        let glyph = Points::from([(1, 0), (1, 1)]);
+
+        let expected = vec![
+            "┏━━━━┓ ┏━━━━┓ ┏━━━━┓",
+            "┃████┃ ┃  ██┃ ┃░░██┃",
+            "┃    ┃ ┃  ██┃ ┃  ▒▒┃",
+            "┗━━━━┛ ┗━━━━┛ ┗━━━━┛",
+        ];
+        let result = compare_cell((2, 2), &partition, &glyph);
+
+        assert_eq!(
+            expected, result,
+            "Expected: {expected:?}, but got: {result:?}"
+        );
+    }
+}